@@ -0,0 +1,36 @@
+//! Converts a Granny2 file to another byte order and pointer size
+//!
+//! `cargo run --example convert -- <input> <output> <le32|le64|be32|be64>`
+
+use std::fs;
+use std::process::exit;
+use opengr2::{convert, FileFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: {} <input> <output> <le32|le64|be32|be64>", args[0]);
+        exit(1);
+    }
+
+    let format = match args[3].as_str() {
+        "le32" => FileFormat::LITTLE_ENDIAN_32,
+        "le64" => FileFormat::LITTLE_ENDIAN_64,
+        "be32" => FileFormat::BIG_ENDIAN_32,
+        "be64" => FileFormat::BIG_ENDIAN_64,
+        format => {
+            eprintln!("unknown format {}", format);
+            exit(1);
+        }
+    };
+
+    let result = fs::read(&args[1])
+        .map_err(Into::into)
+        .and_then(|bytes| convert(&bytes, format))
+        .and_then(|converted| fs::write(&args[2], converted).map_err(Into::into));
+
+    if let Err(error) = result {
+        eprintln!("failed to convert {}: {}", args[1], error);
+        exit(1);
+    }
+}
\ No newline at end of file
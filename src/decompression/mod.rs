@@ -0,0 +1,114 @@
+mod oodle1;
+
+use std::borrow::Cow;
+use crate::error::GrannyError;
+use crate::parser::SectorInfo;
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CompressionType {
+    None,
+    Oodle0,
+    Oodle1,
+    Bitknit1,
+    Bitknit2
+}
+
+impl CompressionType {
+    pub fn from_u32(value: u32) -> Option<CompressionType> {
+        match value {
+            0 => Some(CompressionType::None),
+            1 => Some(CompressionType::Oodle0),
+            2 => Some(CompressionType::Oodle1),
+            3 => Some(CompressionType::Bitknit1),
+            4 => Some(CompressionType::Bitknit2),
+            _ => None
+        }
+    }
+}
+
+/// Returns the decompressed data of the given sector, uncompressed data is borrowed from the input.
+///
+/// NOTE: There are no Oodle-0, BitKnit-1 or BitKnit-2 decoders yet, those sectors are reported as
+/// [GrannyError::UnsupportedCompression] instead of being decoded.
+pub fn decompress_sector<'a>(input: &'a [u8], sector: &SectorInfo) -> Result<Cow<'a, [u8]>, GrannyError> {
+    let start = sector.data_offset as usize;
+    let end = start.checked_add(sector.compressed_length as usize).ok_or(GrannyError::TruncatedData)?;
+    let sector_data = input.get(start..end).ok_or(GrannyError::TruncatedData)?;
+
+    let compression_type = CompressionType::from_u32(sector.compression_type)
+        .ok_or(GrannyError::UnsupportedCompression(sector.compression_type))?;
+
+    match compression_type {
+        CompressionType::None => Ok(Cow::Borrowed(sector_data)),
+        CompressionType::Oodle1 => {
+            oodle1::decompress(sector_data, sector.oodle_stop_0, sector.oodle_stop_1, sector.decompressed_length)
+                .map(Cow::Owned)
+                .ok_or(GrannyError::DecompressionFailed)
+        }
+        CompressionType::Oodle0 | CompressionType::Bitknit1 | CompressionType::Bitknit2 => {
+            Err(GrannyError::UnsupportedCompression(sector.compression_type))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use crate::decompression::decompress_sector;
+    use crate::error::GrannyError;
+    use crate::parser::SectorInfo;
+
+    fn sector_info(compression_type: u32, data_offset: u32, length: u32) -> SectorInfo {
+        SectorInfo {
+            compression_type,
+            data_offset,
+            compressed_length: length,
+            decompressed_length: length,
+            alignment: 4,
+            oodle_stop_0: length,
+            oodle_stop_1: length,
+            fixup_offset: 0,
+            fixup_size: 0,
+            marshall_offset: 0,
+            marshall_size: 0
+        }
+    }
+
+    #[test]
+    fn test_uncompressed() {
+        let input = [0u8, 1, 2, 3, 4, 5];
+
+        let data = decompress_sector(&input, &sector_info(0, 2, 3)).unwrap();
+        assert!(matches!(data, Cow::Borrowed([2, 3, 4])));
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let input = [0u8, 1, 2, 3];
+
+        assert!(matches!(decompress_sector(&input, &sector_info(0, 2, 3)), Err(GrannyError::TruncatedData)));
+    }
+
+    #[test]
+    fn test_oodle0_is_unsupported() {
+        let input = [0u8; 16];
+
+        assert!(matches!(decompress_sector(&input, &sector_info(1, 0, 16)), Err(GrannyError::UnsupportedCompression(1))));
+    }
+
+    #[test]
+    fn test_bitknit_is_unsupported() {
+        let input = [0u8; 16];
+
+        assert!(matches!(decompress_sector(&input, &sector_info(3, 0, 16)), Err(GrannyError::UnsupportedCompression(3))));
+        assert!(matches!(decompress_sector(&input, &sector_info(4, 0, 16)), Err(GrannyError::UnsupportedCompression(4))));
+    }
+
+    #[test]
+    fn test_unknown_compression() {
+        let input = [0u8; 16];
+
+        assert!(matches!(decompress_sector(&input, &sector_info(42, 0, 16)), Err(GrannyError::UnsupportedCompression(42))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,635 @@
+//! Decoder for the Oodle-1 compression used by Granny 2.x sectors.
+//!
+//! The compressed stream starts with three [Parameters] blocks, one for each of the regions a
+//! sector is split into (32 bit aligned data up to `oodle_stop_0`, 16 bit aligned data up to
+//! `oodle_stop_1` and the remaining 8 bit data). Every region is decoded with its own adaptive
+//! [Dictionary] while the arithmetic [Decoder] continues across the region boundaries.
+
+const PARAMETERS_SIZE: usize = 12;
+const RANGE_MAX: u32 = 0x4000;
+const BACKREF_SIZES: [u32; 4] = [128, 192, 256, 512];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Parameters {
+    decoded_value_max: u32,
+    backref_value_max: u32,
+    decoded_count: u32,
+    highbit_count: u32,
+    sizes_count: [u32; 4],
+}
+
+impl Parameters {
+    fn parse(input: &[u8]) -> Option<Parameters> {
+        let input = input.get(..PARAMETERS_SIZE)?;
+
+        let first = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
+        let second = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
+
+        Some(Parameters {
+            decoded_value_max: first & 0x1FF,
+            backref_value_max: first >> 9,
+            decoded_count: second & 0x1FF,
+            highbit_count: second >> 19,
+            sizes_count: [input[8] as u32, input[9] as u32, input[10] as u32, input[11] as u32],
+        })
+    }
+}
+
+/// Arithmetic decoder reading its input with a one bit offset
+struct Decoder<'a> {
+    numerator: u32,
+    denominator: u32,
+    next_denominator: u32,
+    stream: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(stream: &'a [u8]) -> Decoder<'a> {
+        Decoder {
+            numerator: (stream.first().copied().unwrap_or(0) >> 1) as u32,
+            denominator: 0x80,
+            next_denominator: 0,
+            stream,
+            position: 0,
+        }
+    }
+
+    fn byte(&self, index: usize) -> u32 {
+        self.stream.get(index).copied().unwrap_or(0) as u32
+    }
+
+    fn renormalize(&mut self) {
+        while self.denominator <= 0x800000 {
+            let bits = ((self.byte(self.position) << 8 | self.byte(self.position + 1)) >> 1) & 0xFF;
+
+            self.numerator = self.numerator.wrapping_shl(8) | bits;
+            self.denominator <<= 8;
+            self.position += 1;
+        }
+    }
+
+    fn decode(&mut self, max: u32) -> u32 {
+        self.renormalize();
+        self.next_denominator = self.denominator / max;
+
+        (self.numerator / self.next_denominator).min(max - 1)
+    }
+
+    fn commit(&mut self, max: u32, value: u32, error: u32) -> u32 {
+        self.numerator = self.numerator.wrapping_sub(self.next_denominator * value);
+
+        if value + error < max {
+            self.denominator = self.next_denominator * error;
+        } else {
+            self.denominator -= self.next_denominator * value;
+        }
+
+        value
+    }
+
+    fn decode_and_commit(&mut self, max: u32) -> u32 {
+        let value = self.decode(max);
+        self.commit(max, value, 1)
+    }
+}
+
+/// Adaptive frequency table mapping the decoded ranges to values
+#[derive(Clone)]
+struct WeighWindow {
+    count_cap: usize,
+    ranges: Vec<u32>,
+    values: Vec<u32>,
+    weights: Vec<u32>,
+    weight_total: u32,
+    thresh_increase: u32,
+    thresh_increase_cap: u32,
+    thresh_range_rebuild: u32,
+    thresh_weight_rebuild: u32,
+}
+
+impl WeighWindow {
+    fn new(max_value: u32, count_cap: u32) -> WeighWindow {
+        let thresh_weight_rebuild = (32 * max_value).clamp(256, 15160);
+        let thresh_increase_cap = if max_value > 64 {
+            (2 * max_value).min(thresh_weight_rebuild / 2 - 32)
+        } else {
+            128
+        };
+
+        WeighWindow {
+            count_cap: count_cap as usize + 1,
+            ranges: vec![0, RANGE_MAX],
+            values: vec![0],
+            weights: vec![4],
+            weight_total: 4,
+            thresh_increase: 4,
+            thresh_increase_cap,
+            thresh_range_rebuild: 8,
+            thresh_weight_rebuild,
+        }
+    }
+
+    fn rebuild_ranges(&mut self) {
+        let range_weight = 8 * RANGE_MAX / self.weight_total.max(1);
+
+        self.ranges.clear();
+
+        let mut range_start = 0;
+        for weight in &self.weights {
+            self.ranges.push(range_start);
+            range_start += weight * range_weight / 8;
+        }
+
+        self.ranges.push(RANGE_MAX);
+
+        if self.thresh_increase > self.thresh_increase_cap / 2 {
+            self.thresh_range_rebuild = self.weight_total + self.thresh_increase_cap;
+        } else {
+            self.thresh_increase *= 2;
+            self.thresh_range_rebuild = self.weight_total + self.thresh_increase;
+        }
+    }
+
+    fn rebuild_weights(&mut self) {
+        for weight in self.weights.iter_mut() {
+            *weight /= 2;
+        }
+
+        self.weight_total = self.weights.iter().sum();
+
+        let mut i = 1;
+        while i < self.weights.len() {
+            while i < self.weights.len() && self.weights[i] == 0 {
+                self.weights.swap_remove(i);
+                self.values.swap_remove(i);
+            }
+
+            i += 1;
+        }
+
+        if self.weights.len() > 1 {
+            let mut max_index = 1;
+            for i in 2..self.weights.len() {
+                if self.weights[i] > self.weights[max_index] {
+                    max_index = i;
+                }
+            }
+
+            let last = self.weights.len() - 1;
+
+            self.weights.swap(max_index, last);
+            self.values.swap(max_index, last);
+        }
+
+        if self.weights.len() < self.count_cap && self.weights[0] == 0 {
+            self.weights[0] = 1;
+            self.weight_total += 1;
+        }
+    }
+
+    fn prepare(&mut self) {
+        if self.weight_total >= self.thresh_range_rebuild {
+            if self.thresh_range_rebuild >= self.thresh_weight_rebuild {
+                self.rebuild_weights();
+            }
+
+            self.rebuild_ranges();
+        }
+    }
+
+    /// Decodes the next value of this window, returns `None` if the value has to be decoded
+    /// directly from the stream.
+    fn try_decode(&mut self, decoder: &mut Decoder) -> Option<usize> {
+        self.prepare();
+
+        let value = decoder.decode(RANGE_MAX);
+        let index = self.ranges.partition_point(|&range| range <= value) - 1;
+
+        decoder.commit(RANGE_MAX, self.ranges[index], self.ranges[index + 1] - self.ranges[index]);
+
+        self.weights[index] += 1;
+        self.weight_total += 1;
+
+        if index > 0 {
+            return Some(index);
+        }
+
+        if self.weights.len() >= self.ranges.len() && decoder.decode_and_commit(2) == 1 {
+            let pending = (self.weights.len() - self.ranges.len() + 1) as u32;
+            let index = self.ranges.len() + decoder.decode_and_commit(pending) as usize - 1;
+
+            self.weights[index] += 2;
+            self.weight_total += 2;
+
+            return Some(index);
+        }
+
+        self.values.push(0);
+        self.weights.push(2);
+        self.weight_total += 2;
+
+        if self.weights.len() == self.count_cap {
+            self.weight_total -= self.weights[0];
+            self.weights[0] = 0;
+        }
+
+        None
+    }
+
+    fn decode(&mut self, decoder: &mut Decoder, max: u32) -> u32 {
+        match self.try_decode(decoder) {
+            Some(index) => self.values[index],
+            None => {
+                let value = decoder.decode_and_commit(max);
+                *self.values.last_mut().unwrap() = value;
+
+                value
+            }
+        }
+    }
+}
+
+struct Dictionary {
+    decoded_value_max: u32,
+    backref_value_max: u32,
+    lowbit_value_max: u32,
+    midbit_value_max: u32,
+    backref_size: usize,
+    lowbit_window: WeighWindow,
+    highbit_window: WeighWindow,
+    midbit_windows: Vec<WeighWindow>,
+    decoded_windows: Vec<WeighWindow>,
+    size_windows: Vec<WeighWindow>,
+}
+
+impl Dictionary {
+    fn new(parameters: &Parameters) -> Dictionary {
+        let decoded_value_max = parameters.decoded_value_max.max(1);
+        let backref_value_max = parameters.backref_value_max;
+
+        let lowbit_value_max = (backref_value_max + 1).min(4);
+        let midbit_value_max = (backref_value_max / 4 + 1).min(256);
+        let highbit_value_max = backref_value_max / 1024 + 1;
+
+        let mut size_windows = Vec::with_capacity(65);
+        for i in 0..4 {
+            for _ in 0..16 {
+                size_windows.push(WeighWindow::new(64, parameters.sizes_count[3 - i]));
+            }
+        }
+        size_windows.push(WeighWindow::new(64, parameters.sizes_count[0]));
+
+        Dictionary {
+            decoded_value_max,
+            backref_value_max,
+            lowbit_value_max,
+            midbit_value_max,
+            backref_size: 0,
+            lowbit_window: WeighWindow::new(lowbit_value_max - 1, lowbit_value_max),
+            highbit_window: WeighWindow::new(highbit_value_max - 1, parameters.highbit_count + 1),
+            midbit_windows: vec![WeighWindow::new(midbit_value_max - 1, midbit_value_max); highbit_value_max as usize],
+            decoded_windows: vec![WeighWindow::new(decoded_value_max - 1, parameters.decoded_count); 4],
+            size_windows,
+        }
+    }
+
+    /// Decodes either a single literal or a back reference into `output` at `position`, returns
+    /// the amount of written bytes or `None` if the data is invalid.
+    fn decompress_block(&mut self, decoder: &mut Decoder, output: &mut [u8], position: usize) -> Option<usize> {
+        self.backref_size = self.size_windows[self.backref_size].decode(decoder, 65) as usize;
+
+        if self.backref_size > 0 {
+            let size = if self.backref_size < 61 {
+                self.backref_size + 1
+            } else {
+                BACKREF_SIZES[self.backref_size - 61] as usize
+            };
+
+            let lowbit = self.lowbit_window.decode(decoder, self.lowbit_value_max);
+            let highbit = self.highbit_window.decode(decoder, self.backref_value_max / 1024 + 1);
+            let midbit = self.midbit_windows.get_mut(highbit as usize)?.decode(decoder, self.midbit_value_max);
+
+            let offset = ((highbit << 10) + (midbit << 2) + lowbit + 1) as usize;
+            if offset > position {
+                return None;
+            }
+
+            let size = size.min(output.len() - position);
+            for i in position..position + size {
+                output[i] = output[i - offset];
+            }
+
+            Some(size)
+        } else {
+            let value = self.decoded_windows[position % 4].decode(decoder, self.decoded_value_max);
+            output[position] = value as u8;
+
+            Some(1)
+        }
+    }
+}
+
+/// Decompresses an Oodle-1 compressed sector into a buffer of `decompressed_length` bytes.
+pub fn decompress(input: &[u8], stop_0: u32, stop_1: u32, decompressed_length: u32) -> Option<Vec<u8>> {
+    let mut output = vec![0u8; decompressed_length as usize];
+    if output.is_empty() {
+        return Some(output);
+    }
+
+    let parameters = [
+        Parameters::parse(input)?,
+        Parameters::parse(input.get(PARAMETERS_SIZE..)?)?,
+        Parameters::parse(input.get(PARAMETERS_SIZE * 2..)?)?,
+    ];
+
+    let mut decoder = Decoder::new(input.get(PARAMETERS_SIZE * 3..)?);
+
+    let stops = [stop_0 as usize, stop_1 as usize, output.len()];
+    let mut position = 0;
+
+    for (parameters, stop) in parameters.iter().zip(stops) {
+        let stop = stop.min(output.len());
+        let mut dictionary = Dictionary::new(parameters);
+
+        while position < stop {
+            position += dictionary.decompress_block(&mut decoder, &mut output, position)?;
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, Dictionary, Parameters, RANGE_MAX, WeighWindow};
+
+    /// Arithmetic encoder mirroring the decoder, used to produce the test data
+    struct Encoder {
+        /// Digits of the encoded value, the first digit has 7 bits and all following 8 bits
+        digits: Vec<u32>,
+        range: u32,
+    }
+
+    impl Encoder {
+        fn new() -> Encoder {
+            Encoder {
+                digits: vec![0],
+                range: 0x80,
+            }
+        }
+
+        fn add(&mut self, mut value: u64) {
+            for digit in self.digits.iter_mut().rev() {
+                let sum = *digit as u64 + (value & 0xFF);
+
+                *digit = (sum & 0xFF) as u32;
+                value = (value >> 8) + (sum >> 8);
+            }
+
+            assert_eq!(value, 0);
+            assert!(self.digits[0] < 0x80);
+        }
+
+        fn encode(&mut self, max: u32, value: u32, error: u32) {
+            while self.range <= 0x800000 {
+                self.digits.push(0);
+                self.range <<= 8;
+            }
+
+            let next = self.range / max;
+            self.add(next as u64 * value as u64);
+
+            if value + error < max {
+                self.range = next * error;
+            } else {
+                self.range -= next * value;
+            }
+        }
+
+        fn finish(self) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            let mut accumulator = 0u32;
+            let mut bits = 0;
+
+            for (i, digit) in self.digits.iter().enumerate() {
+                let width = if i == 0 { 7 } else { 8 };
+
+                accumulator = accumulator << width | digit;
+                bits += width;
+
+                while bits >= 8 {
+                    bits -= 8;
+                    bytes.push((accumulator >> bits) as u8);
+                    accumulator &= (1 << bits) - 1;
+                }
+            }
+
+            bytes.push((accumulator << (8 - bits)) as u8);
+            bytes.extend([0; 4]);
+
+            bytes
+        }
+    }
+
+    impl WeighWindow {
+        fn encode(&mut self, encoder: &mut Encoder, value: u32, max: u32) {
+            self.prepare();
+
+            let covered = self.ranges.len() - 1;
+            let index = (1..covered).find(|&i| self.values[i] == value && self.ranges[i + 1] > self.ranges[i]);
+
+            if let Some(index) = index {
+                encoder.encode(RANGE_MAX, self.ranges[index], self.ranges[index + 1] - self.ranges[index]);
+
+                self.weights[index] += 1;
+                self.weight_total += 1;
+
+                return;
+            }
+
+            assert!(self.ranges[1] > 0, "Escape symbol is not available");
+            encoder.encode(RANGE_MAX, 0, self.ranges[1]);
+
+            self.weights[0] += 1;
+            self.weight_total += 1;
+
+            if self.weights.len() >= self.ranges.len() {
+                let pending = (self.weights.len() - covered) as u32;
+
+                if let Some(index) = (covered..self.weights.len()).find(|&i| self.values[i] == value) {
+                    encoder.encode(2, 1, 1);
+                    encoder.encode(pending, (index - covered) as u32, 1);
+
+                    self.weights[index] += 2;
+                    self.weight_total += 2;
+
+                    return;
+                }
+
+                encoder.encode(2, 0, 1);
+            }
+
+            self.values.push(value);
+            self.weights.push(2);
+            self.weight_total += 2;
+
+            if self.weights.len() == self.count_cap {
+                self.weight_total -= self.weights[0];
+                self.weights[0] = 0;
+            }
+
+            encoder.encode(max, value, 1);
+        }
+    }
+
+    fn find_match(data: &[u8], position: usize, max_offset: usize) -> Option<(usize, usize, usize)> {
+        let max_length = (data.len() - position).min(512);
+
+        let (length, offset) = (1..=position.min(max_offset))
+            .map(|offset| {
+                let length = (0..max_length)
+                    .take_while(|&i| data[position + i] == data[position + i - offset])
+                    .count();
+
+                (length, offset)
+            })
+            .max_by_key(|&(length, offset)| (length, usize::MAX - offset))?;
+
+        match length {
+            512.. => Some((64, 512, offset)),
+            256.. => Some((63, 256, offset)),
+            192.. => Some((62, 192, offset)),
+            128.. => Some((61, 128, offset)),
+            2.. => Some((length.min(61) - 1, length.min(61), offset)),
+            _ => None
+        }
+    }
+
+    fn compress(data: &[u8], stop_0: usize, stop_1: usize, parameters: &Parameters) -> Vec<u8> {
+        let mut header = Vec::new();
+        for _ in 0..3 {
+            header.extend((parameters.decoded_value_max | parameters.backref_value_max << 9).to_le_bytes());
+            header.extend((parameters.decoded_count | parameters.highbit_count << 19).to_le_bytes());
+            header.extend(parameters.sizes_count.map(|count| count as u8));
+        }
+
+        let mut encoder = Encoder::new();
+        let mut position = 0;
+
+        for stop in [stop_0, stop_1, data.len()] {
+            let mut dictionary = Dictionary::new(parameters);
+
+            while position < stop {
+                let backref = find_match(data, position, parameters.backref_value_max as usize);
+                let size_symbol = backref.map_or(0, |(symbol, _, _)| symbol);
+
+                dictionary.size_windows[dictionary.backref_size].encode(&mut encoder, size_symbol as u32, 65);
+                dictionary.backref_size = size_symbol;
+
+                if let Some((_, size, offset)) = backref {
+                    let offset = (offset - 1) as u32;
+                    let highbit = offset >> 10;
+
+                    dictionary.lowbit_window.encode(&mut encoder, offset & 3, dictionary.lowbit_value_max);
+                    dictionary.highbit_window.encode(&mut encoder, highbit, dictionary.backref_value_max / 1024 + 1);
+                    dictionary.midbit_windows[highbit as usize].encode(&mut encoder, (offset >> 2) & 0xFF, dictionary.midbit_value_max);
+
+                    position += size;
+                } else {
+                    dictionary.decoded_windows[position % 4].encode(&mut encoder, data[position] as u32, dictionary.decoded_value_max);
+
+                    position += 1;
+                }
+            }
+        }
+
+        header.extend(encoder.finish());
+        header
+    }
+
+    fn test_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut seed = 0x1234u32;
+
+        for i in 0..200 {
+            data.extend(format!("Vertex {} Position {} Normal {}; ", i, i * 3 % 17, i % 5).bytes());
+
+            for _ in 0..(i % 7) {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                data.push((seed >> 16) as u8);
+            }
+        }
+
+        data.extend([0u8; 600]);
+        data
+    }
+
+    #[test]
+    fn test_parse_parameters() {
+        let parameters = Parameters::parse(&[0x00, 0x02, 0x00, 0x02, 0x00, 0x01, 0x48, 0x00, 1, 2, 3, 4]).unwrap();
+
+        assert_eq!(parameters, Parameters {
+            decoded_value_max: 0,
+            backref_value_max: 0x10001,
+            decoded_count: 0x100,
+            highbit_count: 9,
+            sizes_count: [1, 2, 3, 4]
+        });
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = test_data();
+        let parameters = Parameters {
+            decoded_value_max: 256,
+            backref_value_max: 4096,
+            decoded_count: 256,
+            highbit_count: 8,
+            sizes_count: [64, 64, 64, 64]
+        };
+
+        let compressed = compress(&data, 1200, 2000, &parameters);
+        assert!(compressed.len() < data.len());
+
+        let decompressed = decompress(&compressed, 1200, 2000, data.len() as u32).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    /// Three regions with one literal each, the second region only allows literals up to 128
+    fn known_answer_input() -> Vec<u8> {
+        let mut input = Vec::new();
+        for decoded_value_max in [256u32, 128, 256] {
+            input.extend(decoded_value_max.to_le_bytes());
+            input.extend(decoded_value_max.to_le_bytes());
+            input.extend([64, 64, 64, 64]);
+        }
+
+        // The first 31 bits are the numerator 0x800000 of 0x80000000. The size symbol is 0 as
+        // 0x800000 / (0x80000000 / 65) = 0, which makes the literal 0x800000 / (33038209 / 256) = 65.
+        input.extend([0x01, 0x00, 0x03, 0x9F]);
+        input
+    }
+
+    #[test]
+    fn test_known_answer() {
+        assert_eq!(decompress(&known_answer_input(), 1, 2, 3), Some(vec![65, 32, 144]));
+    }
+
+    #[test]
+    fn test_region_stops() {
+        // The second literal is decoded with the parameters of the region it is in
+        assert_eq!(decompress(&known_answer_input(), 1, 2, 2), Some(vec![65, 32]));
+        assert_eq!(decompress(&known_answer_input(), 1, 1, 2), Some(vec![65, 64]));
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(decompress(&[], 0, 0, 0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_truncated_parameters() {
+        assert_eq!(decompress(&[0u8; 20], 4, 8, 16), None);
+    }
+}
\ No newline at end of file
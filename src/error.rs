@@ -0,0 +1,123 @@
+use std::fmt::{Display, Formatter};
+use nom::error::{ErrorKind, ParseError};
+
+#[derive(Debug)]
+pub enum GrannyError {
+    /// The file doesn't start with a known Granny2 magic value
+    BadMagic,
+    /// The header, file info or sector table is incomplete
+    TruncatedHeader,
+    /// Data ends before all sectors, fixups or elements could be read
+    TruncatedData,
+    /// A reference points to a sector which doesn't exist
+    SectorOutOfRange(u32),
+    /// A pointer is missing its fixup or points outside of its sector
+    BadPointer { sector: u32, offset: u32 },
+    /// The sector uses a compression which is not supported by this library
+    UnsupportedCompression(u32),
+    /// The compressed sector data is corrupt
+    DecompressionFailed,
+    /// The type tree contains a member type which is unknown
+    UnknownMemberType(u32),
+    /// A string is not valid UTF-8
+    InvalidString,
+    /// A field which is required by the object model is missing
+    MissingField(String),
+    /// A field of the object model has an unexpected element type
+    WrongFieldType(String),
+    /// A bone binding of a mesh references a bone which isn't in the skeleton of its model
+    UnknownBone(String),
+    /// An animation curve uses an unknown encoding
+    UnsupportedCurveFormat(u8),
+    /// The calculated CRC32 doesn't match the one stored in the file info
+    CrcMismatch { expected: u32, actual: u32 },
+    /// The total size stored in the file info doesn't match the length of the data
+    SizeMismatch { expected: u32, actual: usize },
+    /// An object references itself directly or through other objects, so it can't be parsed into a tree
+    CyclicReference { sector: u32, offset: u32 },
+    /// The file could not be read
+    Io(std::io::Error)
+}
+
+impl Display for GrannyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrannyError::BadMagic => write!(f, "unknown file magic"),
+            GrannyError::TruncatedHeader => write!(f, "truncated file header"),
+            GrannyError::TruncatedData => write!(f, "truncated file data"),
+            GrannyError::SectorOutOfRange(sector) => write!(f, "sector {} is out of range", sector),
+            GrannyError::BadPointer { sector, offset } => write!(f, "bad pointer at offset {} in sector {}", offset, sector),
+            GrannyError::UnsupportedCompression(compression) => write!(f, "unsupported compression type {}", compression),
+            GrannyError::DecompressionFailed => write!(f, "failed to decompress sector"),
+            GrannyError::UnknownMemberType(type_id) => write!(f, "unknown member type {}", type_id),
+            GrannyError::InvalidString => write!(f, "string is not valid UTF-8"),
+            GrannyError::MissingField(name) => write!(f, "missing field {}", name),
+            GrannyError::WrongFieldType(name) => write!(f, "field {} has the wrong type", name),
+            GrannyError::UnknownBone(name) => write!(f, "unknown bone {}", name),
+            GrannyError::UnsupportedCurveFormat(format) => write!(f, "unsupported curve format {}", format),
+            GrannyError::CrcMismatch { expected, actual } => write!(f, "CRC32 mismatch (expected {:#010x}, got {:#010x})", expected, actual),
+            GrannyError::SizeMismatch { expected, actual } => write!(f, "file size mismatch (expected {} bytes, got {})", expected, actual),
+            GrannyError::CyclicReference { sector, offset } => write!(f, "cyclic reference to offset {} in sector {}", offset, sector),
+            GrannyError::Io(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+/// `std::io::Error` isn't `Clone`, a cloned `Io` error keeps only its kind and message
+impl Clone for GrannyError {
+    fn clone(&self) -> Self {
+        match self {
+            GrannyError::BadMagic => GrannyError::BadMagic,
+            GrannyError::TruncatedHeader => GrannyError::TruncatedHeader,
+            GrannyError::TruncatedData => GrannyError::TruncatedData,
+            GrannyError::SectorOutOfRange(sector) => GrannyError::SectorOutOfRange(*sector),
+            GrannyError::BadPointer { sector, offset } => GrannyError::BadPointer { sector: *sector, offset: *offset },
+            GrannyError::UnsupportedCompression(compression) => GrannyError::UnsupportedCompression(*compression),
+            GrannyError::DecompressionFailed => GrannyError::DecompressionFailed,
+            GrannyError::UnknownMemberType(type_id) => GrannyError::UnknownMemberType(*type_id),
+            GrannyError::InvalidString => GrannyError::InvalidString,
+            GrannyError::MissingField(name) => GrannyError::MissingField(name.clone()),
+            GrannyError::WrongFieldType(name) => GrannyError::WrongFieldType(name.clone()),
+            GrannyError::UnknownBone(name) => GrannyError::UnknownBone(name.clone()),
+            GrannyError::UnsupportedCurveFormat(format) => GrannyError::UnsupportedCurveFormat(*format),
+            GrannyError::CrcMismatch { expected, actual } => GrannyError::CrcMismatch { expected: *expected, actual: *actual },
+            GrannyError::SizeMismatch { expected, actual } => GrannyError::SizeMismatch { expected: *expected, actual: *actual },
+            GrannyError::CyclicReference { sector, offset } => GrannyError::CyclicReference { sector: *sector, offset: *offset },
+            GrannyError::Io(err) => GrannyError::Io(std::io::Error::new(err.kind(), err.to_string()))
+        }
+    }
+}
+
+impl std::error::Error for GrannyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GrannyError::Io(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for GrannyError {
+    fn from(err: std::io::Error) -> Self {
+        GrannyError::Io(err)
+    }
+}
+
+impl From<nom::Err<GrannyError>> for GrannyError {
+    fn from(err: nom::Err<GrannyError>) -> Self {
+        match err {
+            nom::Err::Incomplete(_) => GrannyError::TruncatedData,
+            nom::Err::Error(err) | nom::Err::Failure(err) => err
+        }
+    }
+}
+
+impl<I> ParseError<I> for GrannyError {
+    fn from_error_kind(_input: I, _kind: ErrorKind) -> Self {
+        GrannyError::TruncatedData
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
\ No newline at end of file
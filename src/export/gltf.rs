@@ -0,0 +1,475 @@
+//! glTF 2.0 export
+//!
+//! Models become nodes with their skeletons as joint hierarchies, meshes are skinned to the bones of
+//! their bone bindings and animations are sampled at their time step. Meshes which aren't bound to a
+//! model are exported as static meshes. The art tool coordinate system and units are converted to
+//! the Y up, meters convention of glTF by the root node. Shear of bone transforms can't be represented
+//! by glTF nodes and is dropped.
+
+use std::fs;
+use std::path::Path;
+use crate::error::GrannyError;
+use crate::export::{diffuse_texture, texture_uri};
+use crate::export::json::Json;
+use crate::granny_file::GrannyFile;
+use crate::model::{Animation, FileInfo, Material, Matrix4, multiply_4x4, Mesh, Model, Skeleton};
+use crate::parser::Transform;
+
+const FLOAT: usize = 5126;
+const UNSIGNED_INT: usize = 5125;
+const UNSIGNED_SHORT: usize = 5123;
+
+struct Node {
+    members: Vec<(&'static str, Json)>,
+    children: Vec<usize>
+}
+
+#[derive(Default)]
+struct Builder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Json>,
+    accessors: Vec<Json>,
+    nodes: Vec<Node>,
+    meshes: Vec<Json>,
+    skins: Vec<Json>,
+    materials: Vec<Material>,
+    images: Vec<String>,
+    animations: Vec<Json>
+}
+
+fn transform_members(transform: &Transform) -> Vec<(&'static str, Json)> {
+    let mut members = Vec::new();
+
+    if transform.flags & Transform::HAS_POSITION != 0 {
+        members.push(("translation", transform.translation.to_vec().into()));
+    }
+    if transform.flags & Transform::HAS_ORIENTATION != 0 {
+        members.push(("rotation", transform.rotation.to_vec().into()));
+    }
+    if transform.flags & Transform::HAS_SCALE_SHEAR != 0 {
+        let scale = [transform.scale_shear[0][0], transform.scale_shear[1][1], transform.scale_shear[2][2]];
+        members.push(("scale", scale.to_vec().into()));
+    }
+
+    members
+}
+
+impl Builder {
+    /// Appends the data as a new buffer view and returns the accessor which describes it
+    fn add_accessor(&mut self, data: Vec<u8>, component_type: usize, count: usize, kind: &'static str, bounds: Option<(Vec<f32>, Vec<f32>)>) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend(&data);
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+
+        self.buffer_views.push(Json::Object(vec![
+            ("buffer", 0usize.into()),
+            ("byteOffset", offset.into()),
+            ("byteLength", data.len().into())
+        ]));
+
+        let mut accessor = vec![
+            ("bufferView", (self.buffer_views.len() - 1).into()),
+            ("componentType", component_type.into()),
+            ("count", count.into()),
+            ("type", kind.into())
+        ];
+        if let Some((min, max)) = bounds {
+            accessor.push(("min", min.into()));
+            accessor.push(("max", max.into()));
+        }
+
+        self.accessors.push(Json::Object(accessor));
+        self.accessors.len() - 1
+    }
+
+    fn add_floats<const N: usize>(&mut self, values: &[[f32; N]], kind: &'static str, with_bounds: bool) -> usize {
+        let data = values.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+
+        let bounds = with_bounds.then(|| {
+            let mut min = vec![f32::MAX; N];
+            let mut max = vec![f32::MIN; N];
+            for value in values {
+                for i in 0..N {
+                    min[i] = min[i].min(value[i]);
+                    max[i] = max[i].max(value[i]);
+                }
+            }
+            (min, max)
+        });
+
+        self.add_accessor(data, FLOAT, values.len(), kind, bounds)
+    }
+
+    fn add_node(&mut self, members: Vec<(&'static str, Json)>, parent: Option<usize>) -> usize {
+        self.nodes.push(Node { members, children: Vec::new() });
+
+        let index = self.nodes.len() - 1;
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    fn material_index(&mut self, material: &Material) -> usize {
+        match self.materials.iter().position(|m| m == material) {
+            Some(index) => index,
+            None => {
+                self.materials.push(material.clone());
+                self.materials.len() - 1
+            }
+        }
+    }
+
+    /// Adds the skeleton bones as nodes below `parent` and returns their node indices
+    fn add_skeleton(&mut self, skeleton: &Skeleton, parent: usize) -> Vec<usize> {
+        let mut nodes: Vec<usize> = Vec::with_capacity(skeleton.bones.len());
+
+        for bone in &skeleton.bones {
+            let parent = usize::try_from(bone.parent_index).ok().and_then(|index| nodes.get(index).copied()).unwrap_or(parent);
+
+            let mut members = vec![("name", bone.name.as_str().into())];
+            members.extend(transform_members(&bone.local_transform));
+
+            nodes.push(self.add_node(members, Some(parent)));
+        }
+
+        nodes
+    }
+
+    /// Adds a skin for the bone bindings of the mesh and returns it with the joint index of every binding
+    ///
+    /// The vertices reference the bindings by index, so a binding of a bone which isn't in the skeleton is an error.
+    fn add_skin(&mut self, mesh: &Mesh, skeleton: &Skeleton, bone_nodes: &[usize]) -> Result<usize, GrannyError> {
+        let bones = mesh.bone_bindings.iter()
+            .map(|binding| skeleton.find_bone(&binding.bone_name).ok_or_else(|| GrannyError::UnknownBone(binding.bone_name.clone())))
+            .collect::<Result<Vec<usize>, GrannyError>>()?;
+
+        let inverse_bind_matrices: Vec<[f32; 16]> = bones.iter()
+            .map(|&bone| flatten(&skeleton.bones[bone].inverse_world_4x4))
+            .collect();
+        let accessor = self.add_floats(&inverse_bind_matrices, "MAT4", false);
+
+        self.skins.push(Json::Object(vec![
+            ("inverseBindMatrices", accessor.into()),
+            ("joints", bones.iter().map(|&bone| bone_nodes[bone]).collect::<Vec<_>>().into())
+        ]));
+        Ok(self.skins.len() - 1)
+    }
+
+    fn add_mesh(&mut self, mesh: &Mesh, skinned: bool) -> Result<usize, GrannyError> {
+        let geometry = mesh.geometry()?;
+        let mut attributes = Vec::new();
+
+        attributes.push(("POSITION", self.add_floats(&geometry.positions, "VEC3", true).into()));
+        if !geometry.normals.is_empty() {
+            attributes.push(("NORMAL", self.add_floats(&geometry.normals, "VEC3", false).into()));
+        }
+        if !geometry.tangents.is_empty() {
+            let tangents: Vec<[f32; 4]> = geometry.tangents.iter().map(|&[x, y, z]| [x, y, z, 1.0]).collect();
+            attributes.push(("TANGENT", self.add_floats(&tangents, "VEC4", false).into()));
+        }
+
+        // glTF has the origin of texture coordinates at the top left instead of the bottom left
+        const TEXCOORDS: [&str; 4] = ["TEXCOORD_0", "TEXCOORD_1", "TEXCOORD_2", "TEXCOORD_3"];
+        for (name, uvs) in TEXCOORDS.iter().zip(&geometry.uvs) {
+            let uvs: Vec<[f32; 2]> = uvs.iter().map(|&[u, v]| [u, 1.0 - v]).collect();
+            attributes.push((name, self.add_floats(&uvs, "VEC2", false).into()));
+        }
+
+        const COLORS: [&str; 4] = ["COLOR_0", "COLOR_1", "COLOR_2", "COLOR_3"];
+        for (name, colors) in COLORS.iter().zip(&geometry.colors) {
+            attributes.push((name, self.add_floats(colors, "VEC4", false).into()));
+        }
+
+        if skinned {
+            let count = geometry.positions.len();
+
+            // Rigid meshes are bound completely to their first bone
+            let (joints, weights) = if geometry.bone_weights.is_empty() {
+                (vec![[0u16; 4]; count], vec![[1.0, 0.0, 0.0, 0.0]; count])
+            } else {
+                (geometry.bone_indices.clone(), geometry.bone_weights.clone())
+            };
+
+            let data = joints.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+            attributes.push(("JOINTS_0", self.add_accessor(data, UNSIGNED_SHORT, joints.len(), "VEC4", None).into()));
+            attributes.push(("WEIGHTS_0", self.add_floats(&weights, "VEC4", false).into()));
+        }
+
+        let attributes = Json::Object(attributes);
+
+        let mut groups: Vec<(Option<usize>, &[u32])> = Vec::new();
+        for group in &mesh.primary_topology.groups {
+            let start = (group.tri_first.max(0) as usize * 3).min(geometry.indices.len());
+            let end = (start + group.tri_count.max(0) as usize * 3).min(geometry.indices.len());
+
+            let material = usize::try_from(group.material_index).ok().and_then(|index| mesh.material_bindings.get(index));
+            groups.push((material.map(|material| self.material_index(material)), &geometry.indices[start..end]));
+        }
+        if groups.is_empty() {
+            groups.push((None, &geometry.indices));
+        }
+
+        let mut primitives = Vec::new();
+        for (material, indices) in groups {
+            let data = indices.iter().flat_map(|v| v.to_le_bytes()).collect();
+            let accessor = self.add_accessor(data, UNSIGNED_INT, indices.len(), "SCALAR", None);
+
+            let mut primitive = vec![("attributes", attributes.clone()), ("indices", accessor.into())];
+            if let Some(material) = material {
+                primitive.push(("material", material.into()));
+            }
+            primitives.push(Json::Object(primitive));
+        }
+
+        self.meshes.push(Json::Object(vec![
+            ("name", mesh.name.as_str().into()),
+            ("primitives", Json::Array(primitives))
+        ]));
+        Ok(self.meshes.len() - 1)
+    }
+
+    fn add_model(&mut self, model: &Model, file_info: &FileInfo, root: usize) -> Result<(), GrannyError> {
+        let mut members = vec![("name", model.name.as_str().into())];
+        members.extend(transform_members(&model.initial_placement));
+        let node = self.add_node(members, Some(root));
+
+        let bone_nodes = match &model.skeleton {
+            Some(skeleton) => self.add_skeleton(skeleton, node),
+            None => Vec::new()
+        };
+
+        for mesh in &model.mesh_bindings {
+            let skeleton = model.skeleton.as_ref().filter(|skeleton| !skeleton.bones.is_empty() && !mesh.bone_bindings.is_empty());
+
+            let mut members = vec![("name", mesh.name.as_str().into()), ("mesh", self.add_mesh(mesh, skeleton.is_some())?.into())];
+            if let Some(skeleton) = skeleton {
+                members.push(("skin", self.add_skin(mesh, skeleton, &bone_nodes)?.into()));
+            }
+            self.add_node(members, Some(node));
+        }
+
+        if let Some(skeleton) = &model.skeleton {
+            for animation in &file_info.animations {
+                self.add_animation(animation, &model.name, skeleton, &bone_nodes)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Samples the animation of the model at every time step
+    fn add_animation(&mut self, animation: &Animation, model_name: &str, skeleton: &Skeleton, bone_nodes: &[usize]) -> Result<(), GrannyError> {
+        let Some(track_group) = animation.find_track_group(model_name).or_else(|| animation.find_track_group(&skeleton.name)) else {
+            return Ok(())
+        };
+
+        let steps = if animation.time_step > 0.0 { (animation.duration / animation.time_step).ceil().max(0.0) as usize } else { 0 };
+        let times: Vec<[f32; 1]> = (0..=steps).map(|i| [(i as f32 * animation.time_step).min(animation.duration.max(0.0))]).collect();
+
+        let sampler = animation.sampler(track_group, skeleton)?;
+        let poses: Vec<_> = times.iter().map(|[time]| sampler.sample(*time)).collect();
+
+        let input = self.add_floats(&times, "SCALAR", true);
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+
+        for track in &track_group.transform_tracks {
+            let Some(bone) = skeleton.find_bone(&track.name) else {
+                continue
+            };
+
+            let translations: Vec<[f32; 3]> = poses.iter().map(|pose| pose.local_transforms[bone].translation).collect();
+            let scales: Vec<[f32; 3]> = poses.iter().map(|pose| {
+                let scale_shear = pose.local_transforms[bone].scale_shear;
+                [scale_shear[0][0], scale_shear[1][1], scale_shear[2][2]]
+            }).collect();
+
+            // Keep consecutive rotations in the same hemisphere so they are interpolated along the short path
+            let mut rotations: Vec<[f32; 4]> = Vec::with_capacity(poses.len());
+            for pose in &poses {
+                let mut rotation = pose.local_transforms[bone].rotation;
+                if let Some(previous) = rotations.last() {
+                    if previous.iter().zip(&rotation).map(|(a, b)| a * b).sum::<f32>() < 0.0 {
+                        rotation = rotation.map(|v| -v);
+                    }
+                }
+                rotations.push(rotation);
+            }
+
+            let outputs = [
+                ("translation", self.add_floats(&translations, "VEC3", false)),
+                ("rotation", self.add_floats(&rotations, "VEC4", false)),
+                ("scale", self.add_floats(&scales, "VEC3", false))
+            ];
+
+            for (path, output) in outputs {
+                samplers.push(Json::Object(vec![("input", input.into()), ("interpolation", "LINEAR".into()), ("output", output.into())]));
+                channels.push(Json::Object(vec![
+                    ("sampler", (samplers.len() - 1).into()),
+                    ("target", Json::Object(vec![("node", bone_nodes[bone].into()), ("path", path.into())]))
+                ]));
+            }
+        }
+
+        if !channels.is_empty() {
+            self.animations.push(Json::Object(vec![
+                ("name", animation.name.as_str().into()),
+                ("channels", Json::Array(channels)),
+                ("samplers", Json::Array(samplers))
+            ]));
+        }
+
+        Ok(())
+    }
+
+    fn build(mut self, file_info: &FileInfo, buffer_uri: Option<&str>) -> Result<(Json, Vec<u8>), GrannyError> {
+        let root = self.add_node(vec![("name", "root".into())], None);
+        if let Some(info) = &file_info.art_tool_info {
+            self.nodes[root].members.push(("matrix", flatten(&coordinate_system(info.right_vector, info.up_vector, info.back_vector, info.origin, info.units_per_meter)).to_vec().into()));
+        }
+
+        for model in &file_info.models {
+            self.add_model(model, file_info, root)?;
+        }
+
+        for mesh in file_info.meshes.iter().filter(|mesh| !file_info.models.iter().any(|model| model.mesh_bindings.contains(mesh))) {
+            let members = vec![("name", mesh.name.as_str().into()), ("mesh", self.add_mesh(mesh, false)?.into())];
+            self.add_node(members, Some(root));
+        }
+
+        let materials = std::mem::take(&mut self.materials);
+        let mut materials_json = Vec::new();
+        for material in &materials {
+            let mut pbr = vec![("metallicFactor", 0.0f32.into())];
+            if let Some(texture) = diffuse_texture(material) {
+                let uri = texture_uri(texture);
+                let index = match self.images.iter().position(|image| *image == uri) {
+                    Some(index) => index,
+                    None => {
+                        self.images.push(uri);
+                        self.images.len() - 1
+                    }
+                };
+                pbr.insert(0, ("baseColorTexture", Json::Object(vec![("index", index.into())])));
+            }
+
+            materials_json.push(Json::Object(vec![("name", material.name.as_str().into()), ("pbrMetallicRoughness", Json::Object(pbr))]));
+        }
+
+        let mut buffer = vec![("byteLength", self.buffer.len().into())];
+        if let Some(uri) = buffer_uri {
+            buffer.insert(0, ("uri", uri.into()));
+        }
+
+        let nodes = self.nodes.into_iter().map(|node| {
+            let mut members = node.members;
+            if !node.children.is_empty() {
+                members.push(("children", node.children.into()));
+            }
+            Json::Object(members)
+        }).collect();
+
+        let mut gltf = vec![
+            ("asset", Json::Object(vec![("version", "2.0".into()), ("generator", "opengr2".into())])),
+            ("scene", 0usize.into()),
+            ("scenes", Json::Array(vec![Json::Object(vec![("nodes", vec![root].into())])])),
+            ("nodes", Json::Array(nodes))
+        ];
+
+        let optional = [
+            ("meshes", self.meshes),
+            ("skins", self.skins),
+            ("materials", materials_json),
+            ("textures", (0..self.images.len()).map(|i| Json::Object(vec![("source", i.into())])).collect()),
+            ("images", self.images.iter().map(|uri| Json::Object(vec![("uri", uri.as_str().into())])).collect()),
+            ("animations", self.animations),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views)
+        ];
+        gltf.extend(optional.into_iter().filter(|(_, values)| !values.is_empty()).map(|(name, values)| (name, Json::Array(values))));
+
+        if !self.buffer.is_empty() {
+            gltf.push(("buffers", Json::Array(vec![Json::Object(buffer)])));
+        }
+
+        Ok((Json::Object(gltf), self.buffer))
+    }
+}
+
+/// Flattens the matrix row by row, which is the column major layout of glTF for our row vector matrices
+fn flatten(matrix: &Matrix4) -> [f32; 16] {
+    let mut result = [0.0; 16];
+    for (value, element) in result.iter_mut().zip(matrix.iter().flatten()) {
+        *value = *element;
+    }
+    result
+}
+
+/// Builds the matrix which converts from the art tool coordinate system to Y up and meters
+fn coordinate_system(right: [f32; 3], up: [f32; 3], back: [f32; 3], origin: [f32; 3], units_per_meter: f32) -> Matrix4 {
+    let scale = if units_per_meter > 0.0 { 1.0 / units_per_meter } else { 1.0 };
+
+    let row = |i: usize| [right[i] * scale, up[i] * scale, back[i] * scale, 0.0];
+    let mut matrix = [row(0), row(1), row(2), [0.0, 0.0, 0.0, 1.0]];
+
+    let translation = multiply_4x4(&[[-origin[0], -origin[1], -origin[2], 1.0], [0.0; 4], [0.0; 4], [0.0; 4]], &matrix)[0];
+    matrix[3][..3].copy_from_slice(&translation[..3]);
+
+    matrix
+}
+
+/// Converts the file into glTF JSON and the binary buffer which is referenced with `buffer_uri`
+pub fn to_gltf(granny_file: &GrannyFile, buffer_uri: &str) -> Result<(String, Vec<u8>), GrannyError> {
+    let (json, buffer) = Builder::default().build(&granny_file.file_info()?, Some(buffer_uri))?;
+
+    Ok((json.to_string(), buffer))
+}
+
+/// Converts the file into a binary glTF (.glb)
+pub fn to_glb(granny_file: &GrannyFile) -> Result<Vec<u8>, GrannyError> {
+    let (json, buffer) = Builder::default().build(&granny_file.file_info()?, None)?;
+
+    let mut json = json.to_string().into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let mut glb = Vec::new();
+    let length = 12 + 8 + json.len() + if buffer.is_empty() { 0 } else { 8 + buffer.len() };
+
+    glb.extend(b"glTF");
+    glb.extend(2u32.to_le_bytes());
+    glb.extend((length as u32).to_le_bytes());
+
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(json);
+
+    if !buffer.is_empty() {
+        glb.extend((buffer.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(buffer);
+    }
+
+    Ok(glb)
+}
+
+/// Writes the file as glTF to `path`, the binary buffer is written next to it with the `bin` extension
+pub fn write_gltf(granny_file: &GrannyFile, path: &str) -> Result<(), GrannyError> {
+    let path = Path::new(path);
+    let buffer_path = path.with_extension("bin");
+    let buffer_uri = buffer_path.file_name().and_then(|name| name.to_str()).unwrap_or("buffer.bin");
+
+    let (json, buffer) = to_gltf(granny_file, buffer_uri)?;
+
+    fs::write(path, json)?;
+    fs::write(path.with_file_name(buffer_uri), buffer)?;
+
+    Ok(())
+}
+
+/// Writes the file as binary glTF to `path`
+pub fn write_glb(granny_file: &GrannyFile, path: &str) -> Result<(), GrannyError> {
+    fs::write(path, to_glb(granny_file)?)?;
+
+    Ok(())
+}
\ No newline at end of file
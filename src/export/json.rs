@@ -0,0 +1,114 @@
+use std::fmt::Write;
+
+/// A minimal JSON value which keeps the order of object members so the output is deterministic
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>)
+}
+
+impl From<f32> for Json {
+    fn from(value: f32) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Json {
+    fn write(&self, output: &mut String) {
+        match self {
+            // JSON has no representation for NaN and infinity
+            Json::Number(value) if !value.is_finite() => output.push('0'),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                let _ = write!(output, "{}", *value as i64);
+            }
+            // Printed as float 32 to get the shortest representation of values which came from the file
+            Json::Number(value) => {
+                let _ = write!(output, "{}", *value as f32);
+            }
+            Json::String(value) => {
+                output.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' => output.push_str("\\\""),
+                        '\\' => output.push_str("\\\\"),
+                        '\n' => output.push_str("\\n"),
+                        '\r' => output.push_str("\\r"),
+                        '\t' => output.push_str("\\t"),
+                        c if (c as u32) < 0x20 => {
+                            let _ = write!(output, "\\u{:04x}", c as u32);
+                        }
+                        c => output.push(c)
+                    }
+                }
+                output.push('"');
+            }
+            Json::Array(values) => {
+                output.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    value.write(output);
+                }
+                output.push(']');
+            }
+            Json::Object(members) => {
+                output.push('{');
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    Json::String(name.to_string()).write(output);
+                    output.push(':');
+                    value.write(output);
+                }
+                output.push('}');
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut output = String::new();
+        self.write(&mut output);
+        f.write_str(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn test_write() {
+        let json = Json::Object(vec![
+            ("name", "a \"b\"\\\n".into()),
+            ("values", vec![1.0f32, 0.5, f32::NAN].into()),
+            ("count", 3usize.into()),
+            ("empty", Json::Object(vec![]))
+        ]);
+
+        assert_eq!(json.to_string(), r#"{"name":"a \"b\"\\\n","values":[1,0.5,0],"count":3,"empty":{}}"#);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,70 @@
+//! Conversion of Granny2 files into other model formats
+
+use crate::model::{Material, Texture};
+
+#[cfg(feature = "gltf")]
+mod json;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod obj;
+
+/// Returns the texture of the material or of its diffuse map
+pub(crate) fn diffuse_texture(material: &Material) -> Option<&Texture> {
+    material.texture.as_ref().or_else(|| {
+        let maps = material.maps.iter().filter_map(|map| map.map.as_ref().map(|material| (&map.usage, material)));
+
+        maps.clone()
+            .filter(|(usage, _)| usage.contains("Diffuse"))
+            .chain(maps)
+            .find_map(|(_, material)| diffuse_texture(material))
+    })
+}
+
+/// Returns the file name of the texture's source image, the stored path is usually absolute on the exporting machine
+pub(crate) fn texture_file_name(texture: &Texture) -> &str {
+    file_name(&texture.from_file_name)
+}
+
+/// Returns the last component of a Windows or Unix path
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or_default()
+}
+
+/// Returns the file name of the texture's source image as a relative URI with reserved characters percent-encoded
+#[cfg(feature = "gltf")]
+pub(crate) fn texture_uri(texture: &Texture) -> String {
+    percent_encode(texture_file_name(texture))
+}
+
+/// Percent-encodes everything except the unreserved characters of RFC 3986
+#[cfg(feature = "gltf")]
+fn percent_encode(value: &str) -> String {
+    let mut uri = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::file_name;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("C:\\Users\\Art\\My Textures\\skin.png"), "skin.png");
+        assert_eq!(file_name("/home/art/skin.png"), "skin.png");
+        assert_eq!(file_name("skin.png"), "skin.png");
+    }
+
+    #[test]
+    #[cfg(feature = "gltf")]
+    fn test_percent_encode() {
+        assert_eq!(super::percent_encode("my skin#1.png"), "my%20skin%231.png");
+        assert_eq!(super::percent_encode("ü.png"), "%C3%BC.png");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Wavefront OBJ and MTL export
+//!
+//! All meshes of the file are written as objects in their mesh space, skinning and animations are
+//! ignored. Every material group of a mesh selects its material, which references the file name of
+//! its diffuse texture.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use crate::error::GrannyError;
+use crate::export::{diffuse_texture, texture_file_name};
+use crate::granny_file::GrannyFile;
+use crate::model::Material;
+
+/// Replaces characters which can't be used in OBJ names
+fn sanitize(name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_whitespace() || c == '#' { '_' } else { c }).collect();
+
+    if name.is_empty() { "unnamed".to_string() } else { name }
+}
+
+/// Converts the meshes of the file into an OBJ and the MTL which is referenced with `mtl_name`
+pub fn to_obj(granny_file: &GrannyFile, mtl_name: &str) -> Result<(String, String), GrannyError> {
+    let file_info = granny_file.file_info()?;
+
+    let mut obj = String::new();
+    let mut materials: Vec<(String, &Material)> = Vec::new();
+    let mut vertex_offset = 1;
+    let mut uv_offset = 1;
+    let mut normal_offset = 1;
+
+    let _ = writeln!(obj, "mtllib {}", mtl_name);
+
+    for mesh in &file_info.meshes {
+        let geometry = mesh.geometry()?;
+        let uvs = geometry.uvs.first().map(Vec::as_slice).unwrap_or_default();
+
+        let _ = writeln!(obj, "o {}", sanitize(&mesh.name));
+        for [x, y, z] in &geometry.positions {
+            let _ = writeln!(obj, "v {} {} {}", x, y, z);
+        }
+        for [u, v] in uvs {
+            let _ = writeln!(obj, "vt {} {}", u, v);
+        }
+        for [x, y, z] in &geometry.normals {
+            let _ = writeln!(obj, "vn {} {} {}", x, y, z);
+        }
+
+        let mut groups: Vec<(Option<&Material>, &[u32])> = Vec::new();
+        for group in &mesh.primary_topology.groups {
+            let start = (group.tri_first.max(0) as usize * 3).min(geometry.indices.len());
+            let end = (start + group.tri_count.max(0) as usize * 3).min(geometry.indices.len());
+
+            let material = usize::try_from(group.material_index).ok().and_then(|index| mesh.material_bindings.get(index));
+            groups.push((material, &geometry.indices[start..end]));
+        }
+        if groups.is_empty() {
+            groups.push((None, &geometry.indices));
+        }
+
+        for (material, indices) in groups {
+            if let Some(material) = material {
+                let name = match materials.iter().find(|(_, m)| *m == material) {
+                    Some((name, _)) => name.clone(),
+                    None => {
+                        // Different materials can share a name but need a unique one in the MTL
+                        let mut name = sanitize(&material.name);
+                        if materials.iter().any(|(n, _)| *n == name) {
+                            name = format!("{}_{}", name, materials.len());
+                        }
+                        materials.push((name.clone(), material));
+                        name
+                    }
+                };
+                let _ = writeln!(obj, "usemtl {}", name);
+            }
+
+            for triangle in indices.chunks_exact(3) {
+                obj.push('f');
+                for &index in triangle {
+                    let index = index as usize;
+                    let _ = match (uvs.is_empty(), geometry.normals.is_empty()) {
+                        (false, false) => write!(obj, " {}/{}/{}", vertex_offset + index, uv_offset + index, normal_offset + index),
+                        (false, true) => write!(obj, " {}/{}", vertex_offset + index, uv_offset + index),
+                        (true, false) => write!(obj, " {}//{}", vertex_offset + index, normal_offset + index),
+                        (true, true) => write!(obj, " {}", vertex_offset + index)
+                    };
+                }
+                obj.push('\n');
+            }
+        }
+
+        vertex_offset += geometry.positions.len();
+        uv_offset += uvs.len();
+        normal_offset += geometry.normals.len();
+    }
+
+    let mut mtl = String::new();
+    for (name, material) in &materials {
+        let _ = writeln!(mtl, "newmtl {}", name);
+        let _ = writeln!(mtl, "Kd 1 1 1");
+        if let Some(texture) = diffuse_texture(material) {
+            let _ = writeln!(mtl, "map_Kd {}", texture_file_name(texture));
+        }
+    }
+
+    Ok((obj, mtl))
+}
+
+/// Writes the meshes of the file as OBJ to `path`, the materials are written next to it with the `mtl` extension
+pub fn write_obj(granny_file: &GrannyFile, path: &str) -> Result<(), GrannyError> {
+    let path = Path::new(path);
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path.file_name().and_then(|name| name.to_str()).unwrap_or("materials.mtl");
+
+    let (obj, mtl) = to_obj(granny_file, mtl_name)?;
+
+    fs::write(path, obj)?;
+    fs::write(&mtl_path, mtl)?;
+
+    Ok(())
+}
\ No newline at end of file
@@ -1,60 +1,256 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::rc::Rc;
 use nom::number::Endianness;
+use crate::crc::crc32;
+use crate::error::GrannyError;
 use crate::granny_path::GrannyResolve;
-use crate::parser::{Element, parse_element, parse_file_info, parse_header, parse_sector_info};
-use crate::sector::load_sector;
+use crate::model::FileInfo;
+use crate::parser::{self, Context, Element, LazyElement, LazyObject, MemberDefinition, ObjectGraph, parse_element, Reference, TypeTree};
+use crate::sector::{load_file_info, load_sectors};
+use crate::writer::write_file;
 
-pub struct GrannyFile {
-    pub root_elements: Vec<Element>
+/// Byte order and pointer size of a file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileFormat {
+    pub big_endian: bool,
+    pub bits_64: bool
 }
 
-impl GrannyFile {
-    pub fn load_from_file(path: &str) -> Option<GrannyFile> {
-        let mut file = File::open(path).ok()?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).ok()?;
+impl FileFormat {
+    pub const LITTLE_ENDIAN_32: FileFormat = FileFormat { big_endian: false, bits_64: false };
+    pub const LITTLE_ENDIAN_64: FileFormat = FileFormat { big_endian: false, bits_64: true };
+    pub const BIG_ENDIAN_32: FileFormat = FileFormat { big_endian: true, bits_64: false };
+    pub const BIG_ENDIAN_64: FileFormat = FileFormat { big_endian: true, bits_64: true };
+}
+
+/// How a mismatch of the total size or CRC32 stored in the file info is handled on load
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Verification {
+    /// Loading fails with `GrannyError::SizeMismatch` or `GrannyError::CrcMismatch`
+    Strict,
+    /// The file is loaded anyway and the mismatches are reported in `GrannyFile::warnings`
+    #[default]
+    Lenient
+}
 
-        GrannyFile::load_from_bytes(buffer.as_ref())
+/// Compares the total size and CRC32 stored in the file info with the data
+fn verify(bytes: &[u8], file_info: &parser::FileInfo) -> Vec<GrannyError> {
+    let mut mismatches = Vec::new();
+
+    if file_info.total_size as usize != bytes.len() {
+        mismatches.push(GrannyError::SizeMismatch { expected: file_info.total_size, actual: bytes.len() });
     }
 
-    pub fn load_from_bytes(bytes: &[u8]) -> Option<GrannyFile> {
-        let (data, header) = parse_header(bytes).ok()?;
+    // The CRC32 covers everything from the sector table to the end of the file
+    let start = (32 + file_info.file_info_size as usize).min(bytes.len());
+    let end = (file_info.total_size as usize).clamp(start, bytes.len());
+    let actual = crc32(&bytes[start..end]);
+    if actual != file_info.crc32 {
+        mismatches.push(GrannyError::CrcMismatch { expected: file_info.crc32, actual });
+    }
 
-        let endianness = if header.big_endian {
-            Endianness::Big
-        } else {
-            Endianness::Little
-        };
+    mismatches
+}
 
-        let (mut data, file_info) = parse_file_info(endianness)(data).ok()?;
+/// Verifies the file and loads its sectors
+fn load_verified(bytes: &[u8], verification: Verification) -> Result<(Context<'_>, parser::FileInfo, Vec<GrannyError>), GrannyError> {
+    let (_, file_info) = load_file_info(bytes)?;
 
-        let mut sectors = Vec::new();
+    let mut warnings = verify(bytes, &file_info);
+    if verification == Verification::Strict && !warnings.is_empty() {
+        return Err(warnings.remove(0))
+    }
 
-        for _ in 0..file_info.sector_count {
-            let (next_input, sector) = parse_sector_info(endianness)(data).ok()?;
+    let (header, file_info, sectors) = load_sectors(bytes)?;
 
-            sectors.push(load_sector(bytes, endianness, sector));
+    let endianness = if header.big_endian {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
 
-            data = next_input;
+    Ok((Context::new(endianness, header.bits_64, sectors), file_info, warnings))
+}
+
+impl Context<'_> {
+    fn format(&self) -> FileFormat {
+        FileFormat {
+            big_endian: self.endianness == Endianness::Big,
+            bits_64: self.is_64bits
         }
+    }
+}
+
+/// A loaded file, names and strings are borrowed from the loaded data unless their sector was compressed
+pub struct GrannyFile<'a> {
+    pub root_elements: Vec<Element<'a>>,
+    /// The format the file was loaded from
+    pub format: FileFormat,
+    /// Identifies the version of the root type, Granny converts files with a different tag on load
+    pub tag: u32,
+    /// Size and CRC32 mismatches found while loading with `Verification::Lenient`
+    pub warnings: Vec<GrannyError>,
+    /// The types of the loaded elements including the types of variants, `None` for files created in code
+    pub types: Option<TypeTree<'a>>
+}
+
+impl GrannyFile<'static> {
+    pub fn load_from_file(path: &str) -> Result<GrannyFile<'static>, GrannyError> {
+        GrannyFile::load_from_file_with(path, Verification::Lenient)
+    }
+
+    pub fn load_from_file_with(path: &str, verification: Verification) -> Result<GrannyFile<'static>, GrannyError> {
+        let mut file = File::open(path)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        GrannyFile::load_from_bytes_with(buffer.as_ref(), verification).map(GrannyFile::into_owned)
+    }
+}
+
+impl<'a> GrannyFile<'a> {
+    /// Loads a file which borrows its names and strings from `bytes`
+    pub fn load_from_bytes(bytes: &'a [u8]) -> Result<GrannyFile<'a>, GrannyError> {
+        GrannyFile::load_from_bytes_with(bytes, Verification::Lenient)
+    }
+
+    /// Loads a file and verifies its total size and CRC32 before any sector is read
+    pub fn load_from_bytes_with(bytes: &'a [u8], verification: Verification) -> Result<GrannyFile<'a>, GrannyError> {
+        let (context, file_info, warnings) = load_verified(bytes, verification)?;
 
         let (_, root) = parse_element(
-            endianness,
-            header.bits_64,
-            &sectors,
+            context.endianness,
+            context.is_64bits,
+            &context.sectors,
             file_info.root_ref.sector,
             file_info.type_ref.sector,
             file_info.root_ref.position,
             file_info.type_ref.position
-        ).ok()?;
+        )?;
+
+        let types = TypeTree::load_with_variants(&context, file_info.type_ref, &root)?;
 
-        Some(GrannyFile {
-            root_elements: root
+        Ok(GrannyFile {
+            root_elements: root,
+            format: context.format(),
+            tag: file_info.tag,
+            warnings,
+            types: Some(types)
         })
     }
 
-    pub fn find_element(&self, path: &str) -> Option<&Element> {
+    /// Copies all borrowed names and strings, so the file outlives the loaded data
+    pub fn into_owned(self) -> GrannyFile<'static> {
+        GrannyFile {
+            root_elements: self.root_elements.into_iter().map(Element::into_owned).collect(),
+            format: self.format,
+            tag: self.tag,
+            warnings: self.warnings,
+            types: self.types.map(TypeTree::into_owned)
+        }
+    }
+
+    /// Writes the file in the given format
+    ///
+    /// Elements keep the member types of `types` as long as they match them, the types of other
+    /// elements are inferred. Objects which are referenced from multiple places are written once
+    /// per reference.
+    pub fn save_to_bytes(&self, format: FileFormat) -> Result<Vec<u8>, GrannyError> {
+        write_file(&self.root_elements, self.types.as_ref(), format, self.tag)
+    }
+
+    pub fn save_to_file(&self, path: &str, format: FileFormat) -> Result<(), GrannyError> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.save_to_bytes(format)?)?;
+
+        Ok(())
+    }
+
+    pub fn find_element(&self, path: &str) -> Option<&Element<'a>> {
         self.root_elements.resolve(path)
     }
+
+    /// Returns the types of the loaded elements, see [GrannyFile::member_definition]
+    pub fn type_tree(&self) -> Option<&TypeTree<'a>> {
+        self.types.as_ref()
+    }
+
+    /// Returns the definition of the member an element was loaded from
+    pub fn member_definition(&self, element: &Element) -> Option<&MemberDefinition<'a>> {
+        self.types.as_ref()?.member(&element.type_ref?)
+    }
+
+    /// Converts the element tree into the typed object model
+    pub fn file_info(&self) -> Result<FileInfo, GrannyError> {
+        FileInfo::from_elements(&self.root_elements)
+    }
+}
+
+/// A loaded file whose objects are parsed when they are first accessed
+///
+/// Only the sectors are loaded up front, so looking up e.g. `ArtToolInfo` doesn't parse any mesh.
+pub struct LazyGrannyFile<'a> {
+    pub root: LazyObject<'a>,
+    /// The format the file was loaded from
+    pub format: FileFormat,
+    pub tag: u32,
+    /// The type of the root object, see [LazyGrannyFile::type_tree]
+    pub type_ref: Reference,
+    /// Size and CRC32 mismatches found while loading with `Verification::Lenient`
+    pub warnings: Vec<GrannyError>
+}
+
+impl<'a> LazyGrannyFile<'a> {
+    pub fn load_from_bytes(bytes: &'a [u8]) -> Result<LazyGrannyFile<'a>, GrannyError> {
+        LazyGrannyFile::load_from_bytes_with(bytes, Verification::Lenient)
+    }
+
+    pub fn load_from_bytes_with(bytes: &'a [u8], verification: Verification) -> Result<LazyGrannyFile<'a>, GrannyError> {
+        let (context, file_info, warnings) = load_verified(bytes, verification)?;
+        let format = context.format();
+
+        let root = LazyObject::new(
+            &Rc::new(context),
+            (file_info.root_ref.sector, file_info.root_ref.position),
+            (file_info.type_ref.sector, file_info.type_ref.position)
+        );
+
+        Ok(LazyGrannyFile {
+            root,
+            format,
+            tag: file_info.tag,
+            type_ref: file_info.type_ref,
+            warnings
+        })
+    }
+
+    pub fn find_element(&self, path: &str) -> Option<&LazyElement<'a>> {
+        self.root.resolve(path)
+    }
+
+    /// Parses the root type and all types which are reachable from it, the types of variants
+    /// can be found with [LazyObject::type_definition]
+    pub fn type_tree(&self) -> Result<TypeTree<'a>, GrannyError> {
+        TypeTree::load(self.root.context(), self.type_ref)
+    }
+
+    /// Parses every object once into a graph which keeps shared objects and cycles
+    pub fn to_object_graph(&self) -> Result<ObjectGraph<'a>, GrannyError> {
+        ObjectGraph::from_root(&self.root)
+    }
+
+    /// Parses the whole file
+    pub fn to_granny_file(&self) -> Result<GrannyFile<'a>, GrannyError> {
+        let root_elements = self.root.to_elements()?;
+        let types = TypeTree::load_with_variants(self.root.context(), self.type_ref, &root_elements)?;
+
+        Ok(GrannyFile {
+            root_elements,
+            format: self.format,
+            tag: self.tag,
+            warnings: self.warnings.clone(),
+            types: Some(types)
+        })
+    }
 }
\ No newline at end of file
@@ -1,34 +1,93 @@
-use crate::parser::{Element, ElementType};
+use crate::error::GrannyError;
+use crate::parser::{Element, ElementType, LazyElement, LazyElementType, LazyObject};
 
+/// Looks up elements by a dotted path (e.g. `Meshes[0].PrimaryVertexData.Vertices[1].Position`)
+///
+/// An index selects an item of an array and has to be followed by the name of a member.
 pub trait GrannyResolve {
-    fn resolve(&self, path: &str) -> Option<&Element>;
+    type Element;
+
+    fn resolve(&self, path: &str) -> Option<&Self::Element>;
+}
+
+fn split_index(segment: &str) -> Option<(&str, Option<usize>)> {
+    match segment.strip_suffix(']').and_then(|s| s.rsplit_once('[')) {
+        Some((name, index)) => Some((name, Some(index.parse().ok()?))),
+        None => Some((segment, None))
+    }
+}
+
+pub(crate) fn split_path(path: &str) -> Option<(&str, Option<usize>, Option<&str>)> {
+    let (segment, rest) = match path.split_once('.') {
+        Some((segment, rest)) => (segment, Some(rest)),
+        None => (path, None)
+    };
+
+    let (name, index) = split_index(segment)?;
+
+    Some((name, index, rest))
 }
 
-impl GrannyResolve for Vec<Element> {
-    fn resolve(&self, path: &str) -> Option<&Element> {
-        let index = path.chars().position(|c| c == '.');
-
-        let name = if let Some(index) = index {
-            &path[0..index]
-        } else {
-            path
-        };
-
-        for e in self {
-            if e.name == name {
-                return if let Some(index) = index {
-                    match &e.element {
-                        ElementType::Reference(elements) => {
-                            elements.resolve(&path[index + 1..])
-                        }
-                        _ => None
-                    }
-                } else {
-                    Some(e)
+impl<'a> GrannyResolve for Vec<Element<'a>> {
+    type Element = Element<'a>;
+
+    fn resolve(&self, path: &str) -> Option<&Element<'a>> {
+        let (name, index, rest) = split_path(path)?;
+
+        let e = self.iter().find(|e| e.name == name)?;
+
+        match (index, rest) {
+            (None, None) => Some(e),
+            (None, Some(rest)) => match &e.element {
+                ElementType::Reference(elements) | ElementType::Inline(elements) | ElementType::Variant { elements, .. } => {
+                    elements.resolve(rest)
+                }
+                _ => None
+            },
+            (Some(index), Some(rest)) => match &e.element {
+                ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) | ElementType::ReferenceToVariantArray(items) => {
+                    items.get(index)?.resolve(rest)
                 }
-            }
+                _ => None
+            },
+            (Some(_), None) => None
         }
+    }
+}
+
+/// Resolves a path in the members of a lazily parsed object, objects are parsed along the path
+pub(crate) fn resolve_lazy<'s, 'a>(elements: &'s [LazyElement<'a>], path: &str) -> Result<Option<&'s LazyElement<'a>>, GrannyError> {
+    let Some((name, index, rest)) = split_path(path) else {
+        return Ok(None)
+    };
+
+    let Some(e) = elements.iter().find(|e| e.name == name) else {
+        return Ok(None)
+    };
+
+    match (index, rest) {
+        (None, None) => Ok(Some(e)),
+        (None, Some(rest)) => match &e.element {
+            LazyElementType::Reference(object) | LazyElementType::Variant { object, .. } => object.try_resolve(rest),
+            LazyElementType::Inline(elements) => resolve_lazy(elements, rest),
+            _ => Ok(None)
+        },
+        (Some(index), Some(rest)) => match &e.element {
+            LazyElementType::ReferenceToArray(items) | LazyElementType::ArrayOfReferences(items) | LazyElementType::ReferenceToVariantArray(items) => match items.get(index)? {
+                Some(item) => item.try_resolve(rest),
+                None => Ok(None)
+            },
+            _ => Ok(None)
+        },
+        (Some(_), None) => Ok(None)
+    }
+}
+
+impl<'a> GrannyResolve for LazyObject<'a> {
+    type Element = LazyElement<'a>;
 
-        None
+    /// Like [LazyObject::try_resolve], but parse errors are treated like missing elements
+    fn resolve(&self, path: &str) -> Option<&LazyElement<'a>> {
+        self.try_resolve(path).ok().flatten()
     }
 }
\ No newline at end of file
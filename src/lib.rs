@@ -3,8 +3,19 @@ extern crate core;
 pub mod parser;
 pub mod decompression;
 pub mod sector;
+pub mod model;
+pub mod export;
+mod crc;
+mod error;
 mod granny_file;
 mod granny_path;
+#[cfg(feature = "mmap")]
+mod mapped_file;
+mod writer;
 
-pub use granny_file::GrannyFile;
-pub use granny_path::GrannyResolve;
\ No newline at end of file
+pub use error::GrannyError;
+pub use granny_file::{FileFormat, GrannyFile, LazyGrannyFile, Verification};
+pub use granny_path::GrannyResolve;
+#[cfg(feature = "mmap")]
+pub use mapped_file::MappedFile;
+pub use writer::convert;
\ No newline at end of file
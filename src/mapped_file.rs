@@ -0,0 +1,40 @@
+use std::fs::File;
+use memmap2::Mmap;
+use crate::error::GrannyError;
+use crate::granny_file::{GrannyFile, LazyGrannyFile, Verification};
+
+/// A memory mapped file which is loaded without reading it into memory first
+///
+/// Uncompressed sectors are parsed directly from the mapping, so a loaded `GrannyFile` borrows the
+/// mapping and can't outlive it.
+pub struct MappedFile {
+    mmap: Mmap
+}
+
+impl MappedFile {
+    /// Maps the file at `path`, it must not be modified while it's mapped
+    pub fn open(path: &str) -> Result<MappedFile, GrannyError> {
+        let file = File::open(path)?;
+        // SAFETY: The mapping is read only, changes to the file by other processes are not supported
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Ok(MappedFile { mmap })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    pub fn load(&self) -> Result<GrannyFile<'_>, GrannyError> {
+        GrannyFile::load_from_bytes(&self.mmap)
+    }
+
+    pub fn load_with(&self, verification: Verification) -> Result<GrannyFile<'_>, GrannyError> {
+        GrannyFile::load_from_bytes_with(&self.mmap, verification)
+    }
+
+    /// Loads the file lazily, so only the accessed objects are read from the mapping
+    pub fn load_lazy(&self) -> Result<LazyGrannyFile<'_>, GrannyError> {
+        LazyGrannyFile::load_from_bytes(&self.mmap)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use crate::error::GrannyError;
+use crate::model::fields::{array, as_real, inline, int, objects, optional, optional_int, real, string, transform};
+use crate::parser::{Element, ElementType, Transform};
+
+/// The still encoded data of an animation curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    /// The type name of the curve data (e.g. `CurveDataHeader_DaK32fC32f`), `None` for empty curves and curves of older files
+    pub type_name: Option<String>,
+    pub elements: Vec<Element<'static>>
+}
+
+impl Curve {
+    pub fn from_elements(elements: &[Element]) -> Result<Curve, GrannyError> {
+        match optional(elements, "CurveData") {
+            Some(ElementType::Variant { type_name, elements }) => Ok(Curve {
+                type_name: type_name.as_deref().map(str::to_string),
+                elements: elements.iter().cloned().map(Element::into_owned).collect()
+            }),
+            Some(_) => Err(GrannyError::WrongFieldType("CurveData".to_string())),
+            // Older files store the knots and controls directly in the curve
+            None => Ok(Curve {
+                type_name: None,
+                elements: elements.iter().cloned().map(Element::into_owned).collect()
+            })
+        }
+    }
+}
+
+/// The curves which animate a single bone
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformTrack {
+    /// The name of the animated bone
+    pub name: String,
+    pub flags: Option<i32>,
+    pub orientation_curve: Curve,
+    pub position_curve: Curve,
+    pub scale_shear_curve: Curve
+}
+
+impl TransformTrack {
+    pub fn from_elements(elements: &[Element]) -> Result<TransformTrack, GrannyError> {
+        Ok(TransformTrack {
+            name: string(elements, "Name")?,
+            flags: optional_int(elements, "Flags")?,
+            orientation_curve: Curve::from_elements(inline(elements, "OrientationCurve")?)?,
+            position_curve: Curve::from_elements(inline(elements, "PositionCurve")?)?,
+            scale_shear_curve: Curve::from_elements(inline(elements, "ScaleShearCurve")?)?
+        })
+    }
+}
+
+/// The tracks which animate the bones of a single model
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackGroup {
+    pub name: String,
+    pub transform_tracks: Vec<TransformTrack>,
+    pub initial_placement: Transform,
+    pub accumulation_flags: i32,
+    pub loop_translation: [f32; 3]
+}
+
+impl TrackGroup {
+    pub fn from_elements(elements: &[Element]) -> Result<TrackGroup, GrannyError> {
+        Ok(TrackGroup {
+            name: string(elements, "Name")?,
+            transform_tracks: objects(elements, "TransformTracks", TransformTrack::from_elements)?,
+            initial_placement: transform(elements, "InitialPlacement")?,
+            accumulation_flags: int(elements, "AccumulationFlags")?,
+            loop_translation: array(elements, "LoopTranslation", as_real)?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: String,
+    /// Length of the animation in seconds
+    pub duration: f32,
+    /// Seconds between two frames as exported by the art tool
+    pub time_step: f32,
+    pub oversampling: f32,
+    pub track_groups: Vec<TrackGroup>,
+    pub default_loop_count: Option<i32>,
+    pub flags: Option<i32>
+}
+
+impl Animation {
+    pub fn from_elements(elements: &[Element]) -> Result<Animation, GrannyError> {
+        Ok(Animation {
+            name: string(elements, "Name")?,
+            duration: real(elements, "Duration")?,
+            time_step: real(elements, "TimeStep")?,
+            oversampling: real(elements, "Oversampling")?,
+            track_groups: objects(elements, "TrackGroups", TrackGroup::from_elements)?,
+            default_loop_count: optional_int(elements, "DefaultLoopCount")?,
+            flags: optional_int(elements, "Flags")?
+        })
+    }
+}
\ No newline at end of file
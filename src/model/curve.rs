@@ -0,0 +1,455 @@
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+use crate::error::GrannyError;
+use crate::model::animation::Curve;
+use crate::model::fields::{array, as_int, as_real, int, real, values};
+use crate::parser::{Element, ElementType};
+
+/// The encodings of the curve data, stored in the `Format` field of the curve data header
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CurveFormat {
+    DaKeyframes32f,
+    DaK32fC32f,
+    DaIdentity,
+    DaConstant32f,
+    D3Constant32f,
+    D4Constant32f,
+    DaK16uC16u,
+    DaK8uC8u,
+    D4nK16uC15u,
+    D4nK8uC7u,
+    D3K16uC16u,
+    D3K8uC8u,
+    D9I1K16uC16u,
+    D9I3K16uC16u,
+    D9I1K8uC8u,
+    D9I3K8uC8u,
+    D3I1K32fC32f,
+    D3I1K16uC16u,
+    D3I1K8uC8u
+}
+
+impl CurveFormat {
+    pub fn from_u8(format: u8) -> Option<CurveFormat> {
+        use CurveFormat::*;
+
+        [
+            DaKeyframes32f, DaK32fC32f, DaIdentity, DaConstant32f, D3Constant32f, D4Constant32f, DaK16uC16u,
+            DaK8uC8u, D4nK16uC15u, D4nK8uC7u, D3K16uC16u, D3K8uC8u, D9I1K16uC16u, D9I3K16uC16u, D9I1K8uC8u,
+            D9I3K8uC8u, D3I1K32fC32f, D3I1K16uC16u, D3I1K8uC8u
+        ].get(format as usize).copied()
+    }
+}
+
+/// A decoded curve, a B-spline of `degree` with one knot per control
+///
+/// `controls` holds `dimension` values per knot. Quaternions are stored as `x, y, z, w` and 3x3
+/// matrices in row major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCurve {
+    pub degree: u8,
+    pub dimension: usize,
+    pub knots: Vec<f32>,
+    pub controls: Vec<f32>
+}
+
+// Quaternion scales and offsets which are selected by `ScaleOffsetTableEntries` of D4n curves
+const QUATERNION_SCALES: [f32; 16] = [
+    SQRT_2, FRAC_1_SQRT_2, 0.35355338, 0.35355338, 0.35355338, 0.17677669, 0.17677669, 0.17677669,
+    -SQRT_2, -FRAC_1_SQRT_2, -0.35355338, -0.35355338, -0.35355338, -0.17677669, -0.17677669, -0.17677669
+];
+const QUATERNION_OFFSETS: [f32; 16] = [
+    -FRAC_1_SQRT_2, -0.35355338, -0.53033006, -0.17677669, 0.17677669, -0.17677669, -0.088388346, 0.0,
+    FRAC_1_SQRT_2, 0.35355338, 0.53033006, 0.17677669, -0.17677669, 0.17677669, 0.088388346, -0.0
+];
+
+fn identity(dimension: usize) -> Vec<f32> {
+    match dimension {
+        4 => vec![0.0, 0.0, 0.0, 1.0],
+        9 => vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        _ => vec![0.0; dimension]
+    }
+}
+
+/// Reads quantized (integer) and unquantized (real) values as float
+fn as_number(element: &ElementType, name: &str) -> Result<f32, GrannyError> {
+    as_real(element, name).or_else(|_| as_int(element, name).map(|value| value as f32))
+}
+
+/// Expands the truncated upper 16 bits of a float 32
+fn truncated_real(elements: &[Element], name: &str) -> Result<f32, GrannyError> {
+    Ok(f32::from_bits((int(elements, name)? as u32 & 0xFFFF) << 16))
+}
+
+/// Splits `KnotsControls` into the knots and the still encoded controls
+fn knots_controls(elements: &[Element], components: usize, one_over_knot_scale: f32) -> Result<(Vec<f32>, Vec<f32>), GrannyError> {
+    let knots_controls = values(elements, "KnotsControls", as_number)?;
+
+    let knot_count = knots_controls.len() / (components + 1);
+    let (knots, controls) = knots_controls.split_at(knot_count);
+
+    Ok((
+        knots.iter().map(|knot| knot / one_over_knot_scale).collect(),
+        controls[..knot_count * components].to_vec()
+    ))
+}
+
+/// Decodes quaternions which are packed into three values with 15 (or 7) bits and a sign bit each
+fn decode_quaternions(controls: &[f32], selector: u32, bits: u32) -> Vec<f32> {
+    let max = ((1 << bits) - 1) as f32;
+    let sign = 1 << bits;
+
+    let scales: Vec<f32> = (0..4).map(|i| QUATERNION_SCALES[(selector >> (i * 4)) as usize & 0xF] / max).collect();
+    let offsets: Vec<f32> = (0..4).map(|i| QUATERNION_OFFSETS[(selector >> (i * 4)) as usize & 0xF]).collect();
+
+    controls.chunks_exact(3).flat_map(|packed| {
+        let [a, b, c] = [packed[0] as u32, packed[1] as u32, packed[2] as u32];
+
+        let largest = (((b & sign) != 0) as usize) << 1 | ((c & sign) != 0) as usize;
+        let components = [(largest + 1) & 3, (largest + 2) & 3, (largest + 3) & 3];
+
+        let mut quaternion = [0.0; 4];
+        for (component, value) in components.into_iter().zip([a, b, c]) {
+            quaternion[component] = (value & (sign - 1)) as f32 * scales[component] + offsets[component];
+        }
+
+        let sum: f32 = quaternion.iter().map(|v| v * v).sum();
+        let w = (1.0 - sum).max(0.0).sqrt();
+        quaternion[largest] = if a & sign != 0 { -w } else { w };
+
+        quaternion
+    }).collect()
+}
+
+/// Scales and offsets every group of `scales.len()` values
+fn dequantize(controls: &[f32], scales: &[f32], offsets: &[f32]) -> Vec<f32> {
+    controls.chunks_exact(scales.len()).flat_map(|control| {
+        control.iter().zip(scales).zip(offsets).map(|((value, scale), offset)| value * scale + offset).collect::<Vec<_>>()
+    }).collect()
+}
+
+/// Turns one (uniform) or three scale values per knot into row major 3x3 diagonal matrices
+fn diagonal(controls: &[f32], components: usize) -> Vec<f32> {
+    controls.chunks_exact(components).flat_map(|scale| {
+        let [x, y, z] = if components == 1 { [scale[0]; 3] } else { [scale[0], scale[1], scale[2]] };
+        [x, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, z]
+    }).collect()
+}
+
+impl Curve {
+    fn header(&self) -> Option<&[Element<'static>]> {
+        match self.elements.first() {
+            Some(Element { name, element: ElementType::Inline(header), .. }) if name.starts_with("CurveDataHeader") => Some(header),
+            _ => None
+        }
+    }
+
+    /// Returns the format from the curve data header, `None` for empty curves and curves of older files
+    pub fn format(&self) -> Option<CurveFormat> {
+        self.header()
+            .and_then(|header| int(header, "Format").ok())
+            .and_then(|format| CurveFormat::from_u8(format as u8))
+    }
+
+    /// Decodes the curve into knots and controls, an empty curve has no knots
+    pub fn decode(&self) -> Result<DecodedCurve, GrannyError> {
+        let elements = &self.elements;
+
+        let header = match self.header() {
+            Some(header) => header,
+            None if !elements.is_empty() => {
+                // Curves written before the curve data formats were introduced store plain knots and controls
+                let knots = values(elements, "Knots", as_real)?;
+                let controls = values(elements, "Controls", as_real)?;
+
+                return Ok(DecodedCurve {
+                    degree: int(elements, "Degree")? as u8,
+                    dimension: controls.len().checked_div(knots.len()).unwrap_or(0),
+                    knots,
+                    controls
+                })
+            }
+            None => {
+                return Ok(DecodedCurve {
+                    degree: 0,
+                    dimension: 0,
+                    knots: Vec::new(),
+                    controls: Vec::new()
+                })
+            }
+        };
+
+        let format = int(header, "Format")? as u8;
+        let degree = int(header, "Degree")? as u8;
+
+        let format = CurveFormat::from_u8(format).ok_or(GrannyError::UnsupportedCurveFormat(format))?;
+
+        let (dimension, knots, controls) = match format {
+            CurveFormat::DaIdentity => {
+                let dimension = int(elements, "Dimension")? as usize;
+                (dimension, vec![0.0], identity(dimension))
+            }
+            CurveFormat::DaConstant32f => {
+                let controls = values(elements, "Controls", as_real)?;
+                (controls.len(), vec![0.0], controls)
+            }
+            CurveFormat::D3Constant32f => {
+                let controls: [f32; 3] = array(elements, "Controls", as_real)?;
+                (3, vec![0.0], controls.to_vec())
+            }
+            CurveFormat::D4Constant32f => {
+                let controls: [f32; 4] = array(elements, "Controls", as_real)?;
+                (4, vec![0.0], controls.to_vec())
+            }
+            CurveFormat::DaKeyframes32f => {
+                let dimension = int(elements, "Dimension")? as usize;
+                let controls = values(elements, "Controls", as_real)?;
+                let knots = (0..controls.len().checked_div(dimension).unwrap_or(0)).map(|i| i as f32).collect();
+                (dimension, knots, controls)
+            }
+            CurveFormat::DaK32fC32f => {
+                let knots = values(elements, "Knots", as_real)?;
+                let controls = values(elements, "Controls", as_real)?;
+                (controls.len().checked_div(knots.len()).unwrap_or(0), knots, controls)
+            }
+            CurveFormat::DaK16uC16u | CurveFormat::DaK8uC8u => {
+                let scale_offsets = values(elements, "ControlScaleOffsets", as_real)?;
+                let dimension = scale_offsets.len() / 2;
+                let (scales, offsets) = scale_offsets.split_at(dimension);
+
+                let (knots, controls) = knots_controls(elements, dimension, truncated_real(elements, "OneOverKnotScaleTrunc")?)?;
+                (dimension, knots, dequantize(&controls, scales, offsets))
+            }
+            CurveFormat::D4nK16uC15u | CurveFormat::D4nK8uC7u => {
+                let selector = int(elements, "ScaleOffsetTableEntries")? as u32;
+                let bits = if format == CurveFormat::D4nK16uC15u { 15 } else { 7 };
+
+                let (knots, controls) = knots_controls(elements, 3, real(elements, "OneOverKnotScale")?)?;
+                (4, knots, decode_quaternions(&controls, selector, bits))
+            }
+            CurveFormat::D3K16uC16u | CurveFormat::D3K8uC8u => {
+                let scales: [f32; 3] = array(elements, "ControlScales", as_real)?;
+                let offsets: [f32; 3] = array(elements, "ControlOffsets", as_real)?;
+
+                let (knots, controls) = knots_controls(elements, 3, truncated_real(elements, "OneOverKnotScaleTrunc")?)?;
+                (3, knots, dequantize(&controls, &scales, &offsets))
+            }
+            CurveFormat::D9I1K16uC16u | CurveFormat::D9I1K8uC8u => {
+                let scale = real(elements, "ControlScale")?;
+                let offset = real(elements, "ControlOffset")?;
+
+                let (knots, controls) = knots_controls(elements, 1, truncated_real(elements, "OneOverKnotScaleTrunc")?)?;
+                (9, knots, diagonal(&dequantize(&controls, &[scale], &[offset]), 1))
+            }
+            CurveFormat::D9I3K16uC16u | CurveFormat::D9I3K8uC8u => {
+                let scales: [f32; 3] = array(elements, "ControlScales", as_real)?;
+                let offsets: [f32; 3] = array(elements, "ControlOffsets", as_real)?;
+
+                let (knots, controls) = knots_controls(elements, 3, truncated_real(elements, "OneOverKnotScaleTrunc")?)?;
+                (9, knots, diagonal(&dequantize(&controls, &scales, &offsets), 3))
+            }
+            CurveFormat::D3I1K32fC32f | CurveFormat::D3I1K16uC16u | CurveFormat::D3I1K8uC8u => {
+                let scales: [f32; 3] = array(elements, "ControlScales", as_real)?;
+                let offsets: [f32; 3] = array(elements, "ControlOffsets", as_real)?;
+
+                let one_over_knot_scale = match format {
+                    CurveFormat::D3I1K32fC32f => 1.0,
+                    _ => truncated_real(elements, "OneOverKnotScaleTrunc")?
+                };
+
+                let (knots, controls) = knots_controls(elements, 1, one_over_knot_scale)?;
+                let controls = controls.iter().flat_map(|&value| [value; 3]).collect::<Vec<_>>();
+                (3, knots, dequantize(&controls, &scales, &offsets))
+            }
+        };
+
+        Ok(DecodedCurve {
+            degree,
+            dimension,
+            knots,
+            controls
+        })
+    }
+}
+
+impl DecodedCurve {
+    /// Evaluates the B-spline at time `t`, times outside of the knots are clamped
+    ///
+    /// The knot of a control is the time at which the control is fully in effect. A control
+    /// and its knot are used for the span which ends with that knot.
+    pub fn evaluate(&self, t: f32) -> Vec<f32> {
+        let count = self.knots.len().min(self.controls.len().checked_div(self.dimension).unwrap_or(0));
+        if count == 0 {
+            return vec![0.0; self.dimension]
+        }
+
+        let last = count as isize - 1;
+        let t = t.clamp(self.knots[0], self.knots[last as usize]);
+
+        let knot = |index: isize| self.knots[index.clamp(0, last) as usize];
+        let control = |index: isize| {
+            let index = index.clamp(0, last) as usize * self.dimension;
+            self.controls[index..index + self.dimension].to_vec()
+        };
+
+        // The first knot at or after t
+        let span = self.knots[..count].iter().position(|&knot| knot >= t).unwrap_or(last as usize) as isize;
+        let degree = self.degree as isize;
+
+        // De Boor's algorithm with the knot vector u(j) = knots[j - 1]
+        let mut points: Vec<Vec<f32>> = (0..=degree).map(|j| control(span - degree + j)).collect();
+        for r in 1..=degree {
+            for j in (r..=degree).rev() {
+                let i = span - degree + j;
+                let start = knot(i - 1);
+                let end = knot(i + degree - r);
+
+                let alpha = if end > start { (t - start) / (end - start) } else { 0.0 };
+
+                let previous = points[j as usize - 1].clone();
+                for (value, previous) in points[j as usize].iter_mut().zip(previous) {
+                    *value = (1.0 - alpha) * previous + alpha * *value;
+                }
+            }
+        }
+
+        points.swap_remove(degree as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::GrannyError;
+    use crate::model::Curve;
+    use crate::parser::{Element, ElementType};
+    use super::{CurveFormat, decode_quaternions};
+
+    fn element(name: &str, element: ElementType<'static>) -> Element<'static> {
+        Element { name: name.to_string().into(), element, type_ref: None }
+    }
+
+    fn list(name: &str, values: &[ElementType<'static>]) -> Element<'static> {
+        element(name, ElementType::ReferenceToArray(values.iter().map(|v| vec![element("Value", v.clone())]).collect()))
+    }
+
+    fn curve(format: CurveFormat, degree: u8, elements: Vec<Element<'static>>) -> Curve {
+        let header = element("CurveDataHeader", ElementType::Inline(vec![
+            element("Format", ElementType::U8(format as u8)),
+            element("Degree", ElementType::U8(degree))
+        ]));
+
+        Curve {
+            type_name: Some("CurveDataHeader".to_string()),
+            elements: [vec![header], elements].concat()
+        }
+    }
+
+    fn reals(values: &[f32]) -> Vec<ElementType<'static>> {
+        values.iter().map(|&v| ElementType::F32(v)).collect()
+    }
+
+    fn assert_near(a: &[f32], b: &[f32]) {
+        assert!(a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_k32f_c32f() {
+        let curve = curve(CurveFormat::DaK32fC32f, 1, vec![
+            element("Padding", ElementType::I16(0)),
+            list("Knots", &reals(&[0.0, 1.0, 3.0])),
+            list("Controls", &reals(&[0.0, 10.0, 2.0, 20.0, 6.0, 40.0]))
+        ]).decode().unwrap();
+
+        assert_eq!(curve.dimension, 2);
+        assert_near(&curve.evaluate(-1.0), &[0.0, 10.0]);
+        assert_near(&curve.evaluate(0.5), &[1.0, 15.0]);
+        assert_near(&curve.evaluate(2.0), &[4.0, 30.0]);
+        assert_near(&curve.evaluate(5.0), &[6.0, 40.0]);
+    }
+
+    #[test]
+    fn test_degree_0_and_2() {
+        let knots = list("Knots", &reals(&[0.0, 1.0, 2.0, 3.0]));
+        let controls = list("Controls", &reals(&[1.0, 1.0, 1.0, 1.0]));
+
+        let step = curve(CurveFormat::DaK32fC32f, 0, vec![knots.clone(), list("Controls", &reals(&[0.0, 1.0, 2.0, 3.0]))]).decode().unwrap();
+        assert_near(&step.evaluate(1.0), &[1.0]);
+        assert_near(&step.evaluate(1.5), &[2.0]);
+
+        // A constant control polygon stays constant for any degree
+        let quadratic = curve(CurveFormat::DaK32fC32f, 2, vec![knots, controls]).decode().unwrap();
+        for t in [0.0, 0.3, 1.7, 3.0] {
+            assert_near(&quadratic.evaluate(t), &[1.0]);
+        }
+
+        // With the knot vector 0, 0, 1, 2, 3, 3 the quadratic basis functions at 1.5 are 1/8, 3/4, 1/8
+        // for the first three controls and at 2.5 they are 1/8, 5/8, 1/4 for the last three
+        let quadratic = curve(CurveFormat::DaK32fC32f, 2, vec![
+            list("Knots", &reals(&[0.0, 1.0, 2.0, 3.0])),
+            list("Controls", &reals(&[0.0, 2.0, 4.0, 8.0]))
+        ]).decode().unwrap();
+        assert_near(&quadratic.evaluate(0.5), &[0.25]);
+        assert_near(&quadratic.evaluate(1.5), &[2.0]);
+        assert_near(&quadratic.evaluate(2.5), &[4.75]);
+    }
+
+    #[test]
+    fn test_d3_k8u_c8u() {
+        let mut knots_controls = vec![ElementType::U8(0), ElementType::U8(10)];
+        knots_controls.extend([0, 0, 0, 255, 255, 255].map(ElementType::U8));
+
+        let curve = curve(CurveFormat::D3K8uC8u, 1, vec![
+            element("OneOverKnotScaleTrunc", ElementType::U16((2.0f32.to_bits() >> 16) as u16)),
+            element("ControlScales", ElementType::Array(reals(&[1.0 / 255.0, 2.0 / 255.0, 1.0 / 255.0]))),
+            element("ControlOffsets", ElementType::Array(reals(&[0.0, 0.0, -1.0]))),
+            list("KnotsControls", &knots_controls)
+        ]).decode().unwrap();
+
+        assert_eq!(curve.knots, vec![0.0, 5.0]);
+        assert_near(&curve.evaluate(2.5), &[0.5, 1.0, -0.5]);
+    }
+
+    #[test]
+    fn test_d9_i1() {
+        let curve = curve(CurveFormat::D9I1K16uC16u, 0, vec![
+            element("OneOverKnotScaleTrunc", ElementType::U16((1.0f32.to_bits() >> 16) as u16)),
+            element("ControlScale", ElementType::F32(0.5)),
+            element("ControlOffset", ElementType::F32(0.0)),
+            list("KnotsControls", &[ElementType::U16(0), ElementType::U16(4)])
+        ]).decode().unwrap();
+
+        assert_eq!(curve.dimension, 9);
+        assert_near(&curve.evaluate(0.0), &[2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_quaternions() {
+        // Largest component is w, all other components use the first table entry which maps 16384 to ~0
+        let identity = decode_quaternions(&[16384.0, (16384 | 0x8000) as f32, (16384 | 0x8000) as f32], 0, 15);
+        assert_near(&identity, &[0.0, 0.0, 0.0, 1.0]);
+
+        // The sign bit of the first value negates the largest component
+        let negated = decode_quaternions(&[(64 | 0x80) as f32, (64 | 0x80) as f32, (64 | 0x80) as f32], 0, 7);
+        assert!(negated[3] < -0.99);
+    }
+
+    #[test]
+    fn test_identity_and_empty() {
+        let orientation = curve(CurveFormat::DaIdentity, 0, vec![element("Dimension", ElementType::I16(4))]).decode().unwrap();
+        assert_near(&orientation.evaluate(1.0), &[0.0, 0.0, 0.0, 1.0]);
+
+        let empty = Curve { type_name: None, elements: vec![] }.decode().unwrap();
+        assert!(empty.knots.is_empty());
+        assert!(empty.evaluate(1.0).is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let mut curve = curve(CurveFormat::DaIdentity, 0, vec![]);
+        curve.elements[0] = element("CurveDataHeader", ElementType::Inline(vec![
+            element("Format", ElementType::U8(42)),
+            element("Degree", ElementType::U8(0))
+        ]));
+
+        assert!(matches!(curve.decode(), Err(GrannyError::UnsupportedCurveFormat(42))));
+    }
+}
\ No newline at end of file
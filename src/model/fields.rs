@@ -0,0 +1,126 @@
+use crate::error::GrannyError;
+use crate::parser::{Element, ElementType, Transform};
+
+pub(crate) fn optional<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Option<&'a ElementType<'e>> {
+    elements.iter().find(|e| e.name == name).map(|e| &e.element)
+}
+
+pub(crate) fn field<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Result<&'a ElementType<'e>, GrannyError> {
+    optional(elements, name).ok_or_else(|| GrannyError::MissingField(name.to_string()))
+}
+
+fn wrong_type(name: &str) -> GrannyError {
+    GrannyError::WrongFieldType(name.to_string())
+}
+
+pub(crate) fn as_string(element: &ElementType, name: &str) -> Result<String, GrannyError> {
+    match element {
+        ElementType::String(value) => Ok(value.to_string()),
+        _ => Err(wrong_type(name))
+    }
+}
+
+pub(crate) fn as_int(element: &ElementType, name: &str) -> Result<i32, GrannyError> {
+    match element {
+        ElementType::I32(value) => Ok(*value),
+        ElementType::I16(value) | ElementType::BinormalI16(value) => Ok(*value as i32),
+        ElementType::U16(value) | ElementType::NormalU16(value) => Ok(*value as i32),
+        ElementType::I8(value) | ElementType::BinormalI8(value) => Ok(*value as i32),
+        ElementType::U8(value) | ElementType::NormalU8(value) => Ok(*value as i32),
+        _ => Err(wrong_type(name))
+    }
+}
+
+pub(crate) fn as_real(element: &ElementType, name: &str) -> Result<f32, GrannyError> {
+    match element {
+        ElementType::F32(value) | ElementType::F16(value) => Ok(*value),
+        _ => Err(wrong_type(name))
+    }
+}
+
+pub(crate) fn string(elements: &[Element], name: &str) -> Result<String, GrannyError> {
+    as_string(field(elements, name)?, name)
+}
+
+pub(crate) fn int(elements: &[Element], name: &str) -> Result<i32, GrannyError> {
+    as_int(field(elements, name)?, name)
+}
+
+pub(crate) fn optional_int(elements: &[Element], name: &str) -> Result<Option<i32>, GrannyError> {
+    optional(elements, name).map(|e| as_int(e, name)).transpose()
+}
+
+pub(crate) fn real(elements: &[Element], name: &str) -> Result<f32, GrannyError> {
+    as_real(field(elements, name)?, name)
+}
+
+pub(crate) fn optional_real(elements: &[Element], name: &str) -> Result<Option<f32>, GrannyError> {
+    optional(elements, name).map(|e| as_real(e, name)).transpose()
+}
+
+/// Reads a fixed size array of values, e.g. `Real32 Origin[3]`
+pub(crate) fn array<T: Default + Copy, const N: usize>(elements: &[Element], name: &str, convert: fn(&ElementType, &str) -> Result<T, GrannyError>) -> Result<[T; N], GrannyError> {
+    match field(elements, name)? {
+        ElementType::Array(values) if values.len() == N => {
+            let mut result = [T::default(); N];
+            for (value, element) in result.iter_mut().zip(values) {
+                *value = convert(element, name)?;
+            }
+
+            Ok(result)
+        }
+        _ => Err(wrong_type(name))
+    }
+}
+
+pub(crate) fn transform(elements: &[Element], name: &str) -> Result<Transform, GrannyError> {
+    match field(elements, name)? {
+        ElementType::Transform(transform) => Ok(*transform),
+        _ => Err(wrong_type(name))
+    }
+}
+
+/// Returns the elements of a reference or `None` if it's a null reference
+pub(crate) fn reference<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Result<Option<&'a [Element<'e>]>, GrannyError> {
+    match field(elements, name)? {
+        ElementType::Reference(elements) if elements.is_empty() => Ok(None),
+        ElementType::Reference(elements) => Ok(Some(elements)),
+        _ => Err(wrong_type(name))
+    }
+}
+
+/// Like [reference] but a null reference is reported as a missing field
+pub(crate) fn required_reference<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Result<&'a [Element<'e>], GrannyError> {
+    reference(elements, name)?.ok_or_else(|| GrannyError::MissingField(name.to_string()))
+}
+
+pub(crate) fn inline<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Result<&'a [Element<'e>], GrannyError> {
+    match field(elements, name)? {
+        ElementType::Inline(elements) => Ok(elements),
+        _ => Err(wrong_type(name))
+    }
+}
+
+/// Returns the items of a reference to an array or of an array of references
+pub(crate) fn items<'a, 'e>(elements: &'a [Element<'e>], name: &str) -> Result<&'a [Vec<Element<'e>>], GrannyError> {
+    match field(elements, name)? {
+        ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) | ElementType::ReferenceToVariantArray(items) => Ok(items),
+        _ => Err(wrong_type(name))
+    }
+}
+
+/// Converts every item of a list into an object
+pub(crate) fn objects<T>(elements: &[Element], name: &str, convert: fn(&[Element]) -> Result<T, GrannyError>) -> Result<Vec<T>, GrannyError> {
+    items(elements, name)?.iter().map(|item| convert(item)).collect()
+}
+
+/// Reads a list of primitive values, e.g. `Int32 *Indices` where every item holds a single value
+pub(crate) fn values<T>(elements: &[Element], name: &str, convert: fn(&ElementType, &str) -> Result<T, GrannyError>) -> Result<Vec<T>, GrannyError> {
+    items(elements, name)?
+        .iter()
+        .map(|item| match item.as_slice() {
+            [value] => convert(&value.element, name),
+            _ => Err(wrong_type(name))
+        })
+        .collect()
+}
\ No newline at end of file
@@ -0,0 +1,152 @@
+use crate::error::GrannyError;
+use crate::model::fields::{as_int, field, optional};
+use crate::model::mesh::{Mesh, TriTopology};
+use crate::parser::{Element, ElementType};
+
+/// Flat vertex and index buffers of a mesh
+///
+/// Every vertex attribute is either empty (if the vertices don't have it) or has one entry per vertex.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshGeometry {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 3]>,
+    pub binormals: Vec<[f32; 3]>,
+    /// One list per texture coordinate set (`TextureCoordinates0`, `TextureCoordinates1`, ...)
+    pub uvs: Vec<Vec<[f32; 2]>>,
+    /// One list per color set (`DiffuseColor0`, `DiffuseColor1`, ...)
+    pub colors: Vec<Vec<[f32; 4]>>,
+    /// Indices into the bone bindings of the mesh
+    pub bone_indices: Vec<[u16; 4]>,
+    pub bone_weights: Vec<[f32; 4]>,
+    /// Triangle list indices
+    pub indices: Vec<u32>
+}
+
+/// Converts a vertex component value to a float, only normalized integers are scaled to -1..1 or 0..1
+fn as_float(element: &ElementType, name: &str) -> Result<f32, GrannyError> {
+    if let Some(value) = element.normalized() {
+        return Ok(value)
+    }
+
+    match element {
+        ElementType::F32(value) | ElementType::F16(value) => Ok(*value),
+        ElementType::I8(value) => Ok(*value as f32),
+        ElementType::U8(value) => Ok(*value as f32),
+        ElementType::I16(value) => Ok(*value as f32),
+        ElementType::U16(value) => Ok(*value as f32),
+        _ => Err(GrannyError::WrongFieldType(name.to_string()))
+    }
+}
+
+fn as_index(element: &ElementType, name: &str) -> Result<u16, GrannyError> {
+    as_int(element, name).map(|value| value as u16)
+}
+
+/// Collects a vertex component of all vertices
+///
+/// Only the first `N` values are kept, components with less values are padded from `default`.
+fn component<T: Copy, const N: usize>(vertices: &[Vec<Element>], name: &str, convert: fn(&ElementType, &str) -> Result<T, GrannyError>, default: [T; N]) -> Result<Vec<[T; N]>, GrannyError> {
+    if vertices.first().and_then(|vertex| optional(vertex, name)).is_none() {
+        return Ok(Vec::new())
+    }
+
+    vertices.iter().map(|vertex| {
+        let mut result = default;
+
+        match field(vertex, name)? {
+            ElementType::Array(values) => {
+                for (value, element) in result.iter_mut().zip(values) {
+                    *value = convert(element, name)?;
+                }
+            }
+            _ => return Err(GrannyError::WrongFieldType(name.to_string()))
+        }
+
+        Ok(result)
+    }).collect()
+}
+
+/// Collects numbered vertex components (e.g. `TextureCoordinates0`, `TextureCoordinates1`) until one is missing
+fn component_sets<const N: usize>(vertices: &[Vec<Element>], prefix: &str, default: [f32; N]) -> Result<Vec<Vec<[f32; N]>>, GrannyError> {
+    let mut sets = Vec::new();
+
+    loop {
+        let set = component(vertices, &format!("{}{}", prefix, sets.len()), as_float, default)?;
+        if set.is_empty() {
+            return Ok(sets)
+        }
+
+        sets.push(set);
+    }
+}
+
+impl TriTopology {
+    /// Returns the triangle indices as 32 bit values regardless of how they are stored
+    pub fn triangle_indices(&self) -> Vec<u32> {
+        if self.indices.is_empty() {
+            self.indices16.iter().map(|&index| index as u32).collect()
+        } else {
+            self.indices.iter().map(|&index| index as u32).collect()
+        }
+    }
+}
+
+impl Mesh {
+    /// Extracts the vertex attributes and triangle indices of the primary vertex data and topology
+    pub fn geometry(&self) -> Result<MeshGeometry, GrannyError> {
+        let vertices = &self.primary_vertex_data.vertices;
+
+        Ok(MeshGeometry {
+            positions: component(vertices, "Position", as_float, [0.0; 3])?,
+            normals: component(vertices, "Normal", as_float, [0.0; 3])?,
+            tangents: component(vertices, "Tangent", as_float, [0.0; 3])?,
+            binormals: component(vertices, "Binormal", as_float, [0.0; 3])?,
+            uvs: component_sets(vertices, "TextureCoordinates", [0.0; 2])?,
+            // Colors without alpha are opaque
+            colors: component_sets(vertices, "DiffuseColor", [0.0, 0.0, 0.0, 1.0])?,
+            bone_indices: component(vertices, "BoneIndices", as_index, [0; 4])?,
+            bone_weights: component(vertices, "BoneWeights", as_float, [0.0; 4])?,
+            indices: self.primary_topology.triangle_indices()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Element, ElementType};
+    use super::{as_float, component, component_sets};
+
+    fn vertex(name: &str, values: &[f32]) -> Vec<Element<'static>> {
+        let values = values.iter().map(|&value| ElementType::F32(value)).collect();
+        vec![Element { name: name.to_string().into(), element: ElementType::Array(values), type_ref: None }]
+    }
+
+    #[test]
+    fn test_normalized_components() {
+        assert_eq!(as_float(&ElementType::NormalU8(255), "").unwrap(), 1.0);
+        assert_eq!(as_float(&ElementType::NormalU8(0), "").unwrap(), 0.0);
+        assert_eq!(as_float(&ElementType::BinormalI8(-128), "").unwrap(), -1.0);
+        assert_eq!(as_float(&ElementType::BinormalI16(i16::MAX), "").unwrap(), 1.0);
+        assert_eq!(as_float(&ElementType::NormalU16(u16::MAX), "").unwrap(), 1.0);
+
+        // Plain integers keep their value
+        assert_eq!(as_float(&ElementType::U8(255), "").unwrap(), 255.0);
+        assert_eq!(as_float(&ElementType::I16(-3), "").unwrap(), -3.0);
+        assert!(as_float(&ElementType::String("".into()), "").is_err());
+    }
+
+    #[test]
+    fn test_component_sizes() {
+        // A color without alpha is opaque
+        let colors = component_sets(&[vertex("DiffuseColor0", &[0.25, 0.5, 0.75])], "DiffuseColor", [0.0, 0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(colors, vec![vec![[0.25, 0.5, 0.75, 1.0]]]);
+
+        // Extra values are ignored
+        let uvs = component_sets(&[vertex("TextureCoordinates0", &[0.25, 0.5, 1.0])], "TextureCoordinates", [0.0; 2]).unwrap();
+        assert_eq!(uvs, vec![vec![[0.25, 0.5]]]);
+
+        let tangents = component(&[vertex("Tangent", &[1.0, 0.0, 0.0, -1.0])], "Tangent", as_float, [0.0; 3]).unwrap();
+        assert_eq!(tangents, vec![[1.0, 0.0, 0.0]]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,56 @@
+use crate::error::GrannyError;
+use crate::model::fields::{array, as_real, int, optional_int, real, string};
+use crate::parser::Element;
+
+/// Information about the art tool which was used to create the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtToolInfo {
+    pub from_art_tool_name: String,
+    pub art_tool_major_revision: i32,
+    pub art_tool_minor_revision: i32,
+    /// Not written by older exporters
+    pub art_tool_pointer_size: Option<i32>,
+    pub units_per_meter: f32,
+    pub origin: [f32; 3],
+    pub right_vector: [f32; 3],
+    pub up_vector: [f32; 3],
+    pub back_vector: [f32; 3]
+}
+
+impl ArtToolInfo {
+    pub fn from_elements(elements: &[Element]) -> Result<ArtToolInfo, GrannyError> {
+        Ok(ArtToolInfo {
+            from_art_tool_name: string(elements, "FromArtToolName")?,
+            art_tool_major_revision: int(elements, "ArtToolMajorRevision")?,
+            art_tool_minor_revision: int(elements, "ArtToolMinorRevision")?,
+            art_tool_pointer_size: optional_int(elements, "ArtToolPointerSize")?,
+            units_per_meter: real(elements, "UnitsPerMeter")?,
+            origin: array(elements, "Origin", as_real)?,
+            right_vector: array(elements, "RightVector", as_real)?,
+            up_vector: array(elements, "UpVector", as_real)?,
+            back_vector: array(elements, "BackVector", as_real)?
+        })
+    }
+}
+
+/// Information about the exporter which wrote the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExporterInfo {
+    pub exporter_name: String,
+    pub exporter_major_revision: i32,
+    pub exporter_minor_revision: i32,
+    pub exporter_customization: i32,
+    pub exporter_build_number: i32
+}
+
+impl ExporterInfo {
+    pub fn from_elements(elements: &[Element]) -> Result<ExporterInfo, GrannyError> {
+        Ok(ExporterInfo {
+            exporter_name: string(elements, "ExporterName")?,
+            exporter_major_revision: int(elements, "ExporterMajorRevision")?,
+            exporter_minor_revision: int(elements, "ExporterMinorRevision")?,
+            exporter_customization: int(elements, "ExporterCustomization")?,
+            exporter_build_number: int(elements, "ExporterBuildNumber")?
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,113 @@
+use crate::error::GrannyError;
+use crate::model::fields::{array, as_int, int, inline, objects, reference, string, values};
+use crate::parser::Element;
+
+/// Describes how the color components are packed into a pixel
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelLayout {
+    pub bytes_per_pixel: i32,
+    pub shift_for_component: [i32; 4],
+    pub bits_for_component: [i32; 4]
+}
+
+impl PixelLayout {
+    pub fn from_elements(elements: &[Element]) -> Result<PixelLayout, GrannyError> {
+        Ok(PixelLayout {
+            bytes_per_pixel: int(elements, "BytesPerPixel")?,
+            shift_for_component: array(elements, "ShiftForComponent", as_int)?,
+            bits_for_component: array(elements, "BitsForComponent", as_int)?
+        })
+    }
+}
+
+/// A single mip level of an embedded texture image
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureMipLevel {
+    pub stride: i32,
+    pub pixel_bytes: Vec<u8>
+}
+
+impl TextureMipLevel {
+    pub fn from_elements(elements: &[Element]) -> Result<TextureMipLevel, GrannyError> {
+        Ok(TextureMipLevel {
+            stride: int(elements, "Stride")?,
+            pixel_bytes: values(elements, "PixelBytes", |e, name| as_int(e, name).map(|v| v as u8))?
+        })
+    }
+}
+
+/// An embedded texture image with all of its mip levels
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureImage {
+    pub mip_levels: Vec<TextureMipLevel>
+}
+
+impl TextureImage {
+    pub fn from_elements(elements: &[Element]) -> Result<TextureImage, GrannyError> {
+        Ok(TextureImage {
+            mip_levels: objects(elements, "MIPLevels", TextureMipLevel::from_elements)?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    /// The path of the texture file which was used by the art tool
+    pub from_file_name: String,
+    pub texture_type: i32,
+    pub width: i32,
+    pub height: i32,
+    pub encoding: i32,
+    pub sub_format: i32,
+    pub layout: PixelLayout,
+    /// Embedded images, empty if the texture is stored externally
+    pub images: Vec<TextureImage>
+}
+
+impl Texture {
+    pub fn from_elements(elements: &[Element]) -> Result<Texture, GrannyError> {
+        Ok(Texture {
+            from_file_name: string(elements, "FromFileName")?,
+            texture_type: int(elements, "TextureType")?,
+            width: int(elements, "Width")?,
+            height: int(elements, "Height")?,
+            encoding: int(elements, "Encoding")?,
+            sub_format: int(elements, "SubFormat")?,
+            layout: PixelLayout::from_elements(inline(elements, "Layout")?)?,
+            images: objects(elements, "Images", TextureImage::from_elements)?
+        })
+    }
+}
+
+/// A sub material with its usage (e.g. `Diffuse Color`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialMap {
+    pub usage: String,
+    pub map: Option<Material>
+}
+
+impl MaterialMap {
+    pub fn from_elements(elements: &[Element]) -> Result<MaterialMap, GrannyError> {
+        Ok(MaterialMap {
+            usage: string(elements, "Usage")?,
+            map: reference(elements, "Map")?.map(Material::from_elements).transpose()?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub maps: Vec<MaterialMap>,
+    pub texture: Option<Texture>
+}
+
+impl Material {
+    pub fn from_elements(elements: &[Element]) -> Result<Material, GrannyError> {
+        Ok(Material {
+            name: string(elements, "Name")?,
+            maps: objects(elements, "Maps", MaterialMap::from_elements)?,
+            texture: reference(elements, "Texture")?.map(Texture::from_elements).transpose()?
+        })
+    }
+}
\ No newline at end of file
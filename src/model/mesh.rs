@@ -0,0 +1,101 @@
+use crate::error::GrannyError;
+use crate::model::fields::{array, as_int, as_real, as_string, int, items, objects, required_reference, string, values};
+use crate::model::material::Material;
+use crate::parser::Element;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexData {
+    /// The vertices, each vertex is a list of its components (e.g. `Position`, `Normal`)
+    pub vertices: Vec<Vec<Element<'static>>>,
+    /// The names of the vertex components as used by the art tool
+    pub vertex_component_names: Vec<String>
+}
+
+impl VertexData {
+    pub fn from_elements(elements: &[Element]) -> Result<VertexData, GrannyError> {
+        Ok(VertexData {
+            vertices: items(elements, "Vertices")?.iter().map(|vertex| vertex.iter().cloned().map(Element::into_owned).collect()).collect(),
+            vertex_component_names: values(elements, "VertexComponentNames", as_string)?
+        })
+    }
+}
+
+/// A range of triangles which use the same material
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriMaterialGroup {
+    /// Index into the material bindings of the mesh
+    pub material_index: i32,
+    pub tri_first: i32,
+    pub tri_count: i32
+}
+
+impl TriMaterialGroup {
+    pub fn from_elements(elements: &[Element]) -> Result<TriMaterialGroup, GrannyError> {
+        Ok(TriMaterialGroup {
+            material_index: int(elements, "MaterialIndex")?,
+            tri_first: int(elements, "TriFirst")?,
+            tri_count: int(elements, "TriCount")?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriTopology {
+    pub groups: Vec<TriMaterialGroup>,
+    /// 32 bit triangle indices, empty if the topology uses `indices16`
+    pub indices: Vec<i32>,
+    /// 16 bit triangle indices, empty if the topology uses `indices`
+    pub indices16: Vec<u16>
+}
+
+impl TriTopology {
+    pub fn from_elements(elements: &[Element]) -> Result<TriTopology, GrannyError> {
+        Ok(TriTopology {
+            groups: objects(elements, "Groups", TriMaterialGroup::from_elements)?,
+            indices: values(elements, "Indices", as_int)?,
+            indices16: values(elements, "Indices16", |e, name| as_int(e, name).map(|v| v as u16))?
+        })
+    }
+}
+
+/// The bounding box of the triangles which are influenced by a bone
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoneBinding {
+    pub bone_name: String,
+    pub obb_min: [f32; 3],
+    pub obb_max: [f32; 3],
+    pub triangle_indices: Vec<i32>
+}
+
+impl BoneBinding {
+    pub fn from_elements(elements: &[Element]) -> Result<BoneBinding, GrannyError> {
+        Ok(BoneBinding {
+            bone_name: string(elements, "BoneName")?,
+            obb_min: array(elements, "OBBMin", as_real)?,
+            obb_max: array(elements, "OBBMax", as_real)?,
+            triangle_indices: values(elements, "TriangleIndices", as_int)?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub name: String,
+    pub primary_vertex_data: VertexData,
+    pub primary_topology: TriTopology,
+    /// The materials which are referenced by `TriMaterialGroup::material_index`
+    pub material_bindings: Vec<Material>,
+    pub bone_bindings: Vec<BoneBinding>
+}
+
+impl Mesh {
+    pub fn from_elements(elements: &[Element]) -> Result<Mesh, GrannyError> {
+        Ok(Mesh {
+            name: string(elements, "Name")?,
+            primary_vertex_data: VertexData::from_elements(required_reference(elements, "PrimaryVertexData")?)?,
+            primary_topology: TriTopology::from_elements(required_reference(elements, "PrimaryTopology")?)?,
+            material_bindings: objects(elements, "MaterialBindings", |binding| Material::from_elements(required_reference(binding, "Material")?))?,
+            bone_bindings: objects(elements, "BoneBindings", BoneBinding::from_elements)?
+        })
+    }
+}
\ No newline at end of file
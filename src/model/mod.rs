@@ -0,0 +1,85 @@
+//! Typed representation of the standard Granny2 file info tree
+//!
+//! Objects which are referenced multiple times in the file (e.g. a mesh which is listed in
+//! `Meshes` and bound to a model) are converted into independent copies.
+
+mod fields;
+mod info;
+mod material;
+mod skeleton;
+mod mesh;
+mod animation;
+mod geometry;
+mod transform;
+mod curve;
+mod pose;
+
+pub use info::*;
+pub use material::*;
+pub use skeleton::*;
+pub use mesh::*;
+pub use animation::*;
+pub use geometry::*;
+pub use transform::*;
+pub use curve::*;
+pub use pose::*;
+
+use crate::error::GrannyError;
+use crate::model::fields::{objects, reference, required_reference, string, transform};
+use crate::parser::{Element, Transform};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub skeleton: Option<Skeleton>,
+    pub initial_placement: Transform,
+    pub mesh_bindings: Vec<Mesh>
+}
+
+impl Model {
+    pub fn from_elements(elements: &[Element]) -> Result<Model, GrannyError> {
+        Ok(Model {
+            name: string(elements, "Name")?,
+            skeleton: reference(elements, "Skeleton")?.map(Skeleton::from_elements).transpose()?,
+            initial_placement: transform(elements, "InitialPlacement")?,
+            mesh_bindings: objects(elements, "MeshBindings", |binding| Mesh::from_elements(required_reference(binding, "Mesh")?))?
+        })
+    }
+}
+
+/// The root object of a Granny2 file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub art_tool_info: Option<ArtToolInfo>,
+    pub exporter_info: Option<ExporterInfo>,
+    /// The path of the source file which was exported
+    pub from_file_name: String,
+    pub textures: Vec<Texture>,
+    pub materials: Vec<Material>,
+    pub skeletons: Vec<Skeleton>,
+    pub vertex_datas: Vec<VertexData>,
+    pub tri_topologies: Vec<TriTopology>,
+    pub meshes: Vec<Mesh>,
+    pub models: Vec<Model>,
+    pub track_groups: Vec<TrackGroup>,
+    pub animations: Vec<Animation>
+}
+
+impl FileInfo {
+    pub fn from_elements(elements: &[Element]) -> Result<FileInfo, GrannyError> {
+        Ok(FileInfo {
+            art_tool_info: reference(elements, "ArtToolInfo")?.map(ArtToolInfo::from_elements).transpose()?,
+            exporter_info: reference(elements, "ExporterInfo")?.map(ExporterInfo::from_elements).transpose()?,
+            from_file_name: string(elements, "FromFileName")?,
+            textures: objects(elements, "Textures", Texture::from_elements)?,
+            materials: objects(elements, "Materials", Material::from_elements)?,
+            skeletons: objects(elements, "Skeletons", Skeleton::from_elements)?,
+            vertex_datas: objects(elements, "VertexDatas", VertexData::from_elements)?,
+            tri_topologies: objects(elements, "TriTopologies", TriTopology::from_elements)?,
+            meshes: objects(elements, "Meshes", Mesh::from_elements)?,
+            models: objects(elements, "Models", Model::from_elements)?,
+            track_groups: objects(elements, "TrackGroups", TrackGroup::from_elements)?,
+            animations: objects(elements, "Animations", Animation::from_elements)?
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+use crate::error::GrannyError;
+use crate::model::animation::{Animation, Curve, TrackGroup};
+use crate::model::curve::{CurveFormat, DecodedCurve};
+use crate::model::skeleton::Skeleton;
+use crate::model::transform::Matrix4;
+use crate::parser::Transform;
+
+/// The transforms of all bones of a skeleton at a point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// One transform per bone relative to its parent
+    pub local_transforms: Vec<Transform>,
+    /// One matrix per bone
+    pub world_matrices: Vec<Matrix4>
+}
+
+impl Curve {
+    /// Decodes the curve for sampling, returns `None` for empty curves
+    fn decode_for_sampling(&self, seconds_per_keyframe: f32) -> Result<Option<DecodedCurve>, GrannyError> {
+        let mut curve = self.decode()?;
+        if curve.knots.is_empty() {
+            return Ok(None)
+        }
+
+        // Keyframe curves store one control per sample instead of knots in seconds
+        if self.format() == Some(CurveFormat::DaKeyframes32f) {
+            curve.knots.iter_mut().for_each(|knot| *knot *= seconds_per_keyframe);
+        }
+
+        Ok(Some(curve))
+    }
+}
+
+/// The decoded curves of a transform track and the bone they animate
+#[derive(Debug, Clone, PartialEq)]
+struct DecodedTrack {
+    bone: usize,
+    position: Option<DecodedCurve>,
+    orientation: Option<DecodedCurve>,
+    scale_shear: Option<DecodedCurve>
+}
+
+/// Samples poses of a skeleton from a track group whose curves are decoded once
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationSampler<'s> {
+    skeleton: &'s Skeleton,
+    duration: f32,
+    tracks: Vec<DecodedTrack>
+}
+
+impl AnimationSampler<'_> {
+    /// Samples the pose at `time` seconds, which is clamped to the duration
+    pub fn sample(&self, time: f32) -> Pose {
+        let time = time.clamp(0.0, self.duration.max(0.0));
+
+        let mut local_transforms: Vec<Transform> = self.skeleton.bones.iter().map(|bone| bone.local_transform).collect();
+
+        for track in &self.tracks {
+            let transform = &mut local_transforms[track.bone];
+
+            if let Some(position) = &track.position {
+                if let Ok(translation) = <[f32; 3]>::try_from(position.evaluate(time).as_slice()) {
+                    transform.translation = translation;
+                    transform.flags |= Transform::HAS_POSITION;
+                }
+            }
+
+            if let Some(orientation) = &track.orientation {
+                let orientation = orientation.evaluate(time);
+                let length = orientation.iter().map(|v| v * v).sum::<f32>().sqrt();
+                if let (Ok(rotation), true) = (<[f32; 4]>::try_from(orientation.as_slice()), length > 0.0) {
+                    transform.rotation = rotation.map(|v| v / length);
+                    transform.flags |= Transform::HAS_ORIENTATION;
+                }
+            }
+
+            if let Some(scale_shear) = &track.scale_shear {
+                let scale_shear = scale_shear.evaluate(time);
+                if scale_shear.len() == 9 {
+                    for (row, values) in transform.scale_shear.iter_mut().zip(scale_shear.chunks_exact(3)) {
+                        row.copy_from_slice(values);
+                    }
+                    transform.flags |= Transform::HAS_SCALE_SHEAR;
+                }
+            }
+        }
+
+        Pose {
+            world_matrices: self.skeleton.world_matrices_from(&local_transforms),
+            local_transforms
+        }
+    }
+}
+
+impl Animation {
+    /// Returns the track group which animates the model with the given name
+    pub fn find_track_group(&self, name: &str) -> Option<&TrackGroup> {
+        self.track_groups.iter().find(|track_group| track_group.name == name)
+    }
+
+    /// Seconds between two samples of keyframe curves, the art tool was sampled `oversampling` times per time step
+    pub fn seconds_per_keyframe(&self) -> f32 {
+        if self.oversampling > 0.0 {
+            self.time_step / self.oversampling
+        } else {
+            self.time_step
+        }
+    }
+
+    /// Decodes the curves of `track_group` to sample many poses of `skeleton`
+    ///
+    /// Tracks are matched to bones by name. Bones without a track keep their local transform.
+    pub fn sampler<'s>(&self, track_group: &TrackGroup, skeleton: &'s Skeleton) -> Result<AnimationSampler<'s>, GrannyError> {
+        let seconds_per_keyframe = self.seconds_per_keyframe();
+
+        let mut tracks = Vec::new();
+        for track in &track_group.transform_tracks {
+            let Some(bone) = skeleton.find_bone(&track.name) else {
+                continue
+            };
+
+            tracks.push(DecodedTrack {
+                bone,
+                position: track.position_curve.decode_for_sampling(seconds_per_keyframe)?,
+                orientation: track.orientation_curve.decode_for_sampling(seconds_per_keyframe)?,
+                scale_shear: track.scale_shear_curve.decode_for_sampling(seconds_per_keyframe)?
+            });
+        }
+
+        Ok(AnimationSampler { skeleton, duration: self.duration, tracks })
+    }
+
+    /// Samples the pose of `skeleton` at `time` seconds, which is clamped to the duration
+    ///
+    /// This decodes the curves on every call, use [Animation::sampler] to sample multiple poses.
+    pub fn sample(&self, track_group: &TrackGroup, skeleton: &Skeleton, time: f32) -> Result<Pose, GrannyError> {
+        Ok(self.sampler(track_group, skeleton)?.sample(time))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use crate::error::GrannyError;
+use crate::model::fields::{array, as_real, int, objects, optional_int, optional_real, string, transform};
+use crate::model::transform::{IDENTITY_4X4, Matrix4, multiply_4x4};
+use crate::parser::{Element, Transform};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bone {
+    pub name: String,
+    /// Index of the parent bone in the skeleton or -1 for root bones
+    pub parent_index: i32,
+    /// The transform relative to the parent bone
+    pub local_transform: Transform,
+    /// Row major inverse of the bone's world transform in the rest pose
+    pub inverse_world_4x4: Matrix4,
+    pub lod_error: Option<f32>
+}
+
+impl Bone {
+    pub fn from_elements(elements: &[Element]) -> Result<Bone, GrannyError> {
+        let inverse_world: [f32; 16] = array(elements, "InverseWorldTransform", as_real)?;
+
+        let mut inverse_world_4x4 = [[0.0; 4]; 4];
+        for (row, values) in inverse_world_4x4.iter_mut().zip(inverse_world.chunks_exact(4)) {
+            row.copy_from_slice(values);
+        }
+
+        Ok(Bone {
+            name: string(elements, "Name")?,
+            parent_index: int(elements, "ParentIndex")?,
+            local_transform: transform(elements, "Transform")?,
+            inverse_world_4x4,
+            lod_error: optional_real(elements, "LODError")?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    pub name: String,
+    pub bones: Vec<Bone>,
+    pub lod_type: Option<i32>
+}
+
+impl Skeleton {
+    pub fn from_elements(elements: &[Element]) -> Result<Skeleton, GrannyError> {
+        Ok(Skeleton {
+            name: string(elements, "Name")?,
+            bones: objects(elements, "Bones", Bone::from_elements)?,
+            lod_type: optional_int(elements, "LODType")?
+        })
+    }
+
+    /// Returns the index of the bone with the given name
+    pub fn find_bone(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|bone| bone.name == name)
+    }
+
+    /// Composes the local transforms of the bones into world matrices of the rest pose
+    pub fn world_matrices(&self) -> Vec<Matrix4> {
+        let local_transforms: Vec<Transform> = self.bones.iter().map(|bone| bone.local_transform).collect();
+
+        self.world_matrices_from(&local_transforms)
+    }
+
+    /// Composes local transforms (one per bone) into world matrices
+    ///
+    /// Granny stores parents before their children, a parent index which doesn't point to an earlier
+    /// bone is treated like a root bone.
+    pub fn world_matrices_from(&self, local_transforms: &[Transform]) -> Vec<Matrix4> {
+        let mut world: Vec<Matrix4> = Vec::with_capacity(self.bones.len());
+
+        for (bone, local_transform) in self.bones.iter().zip(local_transforms) {
+            let parent = usize::try_from(bone.parent_index).ok().and_then(|index| world.get(index)).unwrap_or(&IDENTITY_4X4);
+
+            world.push(multiply_4x4(&local_transform.to_matrix(), parent));
+        }
+
+        world
+    }
+}
\ No newline at end of file
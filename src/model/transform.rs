@@ -0,0 +1,138 @@
+use crate::parser::Transform;
+
+/// A row major 4x4 matrix which is applied to row vectors (`v' = v * M`), like the matrices stored by Granny
+pub type Matrix4 = [[f32; 4]; 4];
+
+pub const IDENTITY_4X4: Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0]
+];
+
+/// Multiplies two matrices, the result applies `a` first and `b` afterwards
+pub fn multiply_4x4(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut result = [[0.0; 4]; 4];
+
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+
+    result
+}
+
+impl Transform {
+    /// The translation is used
+    pub const HAS_POSITION: u32 = 1;
+    /// The rotation is used
+    pub const HAS_ORIENTATION: u32 = 2;
+    /// The scale/shear matrix is used
+    pub const HAS_SCALE_SHEAR: u32 = 4;
+
+    pub fn identity() -> Transform {
+        Transform {
+            flags: 0,
+            translation: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale_shear: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        }
+    }
+
+    /// Composes the transform into a matrix which scales/shears first, then rotates and translates at last
+    ///
+    /// Parts whose flag isn't set are ignored.
+    pub fn to_matrix(&self) -> Matrix4 {
+        let mut linear = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        if self.flags & Transform::HAS_SCALE_SHEAR != 0 {
+            linear = self.scale_shear;
+        }
+
+        if self.flags & Transform::HAS_ORIENTATION != 0 {
+            let [x, y, z, w] = self.rotation;
+            let rotation = [
+                [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w)],
+                [2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w)],
+                [2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y)]
+            ];
+
+            let mut rotated = [[0.0; 3]; 3];
+            for (i, row) in rotated.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value = (0..3).map(|k| linear[i][k] * rotation[k][j]).sum();
+                }
+            }
+            linear = rotated;
+        }
+
+        let mut result = IDENTITY_4X4;
+        for (row, linear) in result.iter_mut().zip(linear) {
+            row[..3].copy_from_slice(&linear);
+        }
+
+        if self.flags & Transform::HAS_POSITION != 0 {
+            result[3][..3].copy_from_slice(&self.translation);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Transform;
+    use super::{IDENTITY_4X4, multiply_4x4};
+
+    fn transform_point(matrix: &[[f32; 4]; 4], point: [f32; 3]) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = point[0] * matrix[0][i] + point[1] * matrix[1][i] + point[2] * matrix[2][i] + matrix[3][i];
+        }
+        result
+    }
+
+    fn assert_near(a: [f32; 3], b: [f32; 3]) {
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_flags() {
+        let transform = Transform {
+            flags: 0,
+            translation: [1.0, 2.0, 3.0],
+            rotation: [0.0, 0.0, 1.0, 0.0],
+            scale_shear: [[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 2.0]]
+        };
+        assert_eq!(transform.to_matrix(), IDENTITY_4X4);
+
+        let transform = Transform { flags: Transform::HAS_POSITION, ..transform };
+        assert_near(transform_point(&transform.to_matrix(), [1.0, 0.0, 0.0]), [2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_composition_order() {
+        // 90 degrees around the z axis
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let transform = Transform {
+            flags: Transform::HAS_POSITION | Transform::HAS_ORIENTATION | Transform::HAS_SCALE_SHEAR,
+            translation: [0.0, 0.0, 5.0],
+            rotation: [0.0, 0.0, half, half],
+            scale_shear: [[2.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        };
+
+        // Scaled to (2, 0, 0), rotated to (0, 2, 0) and then translated
+        assert_near(transform_point(&transform.to_matrix(), [1.0, 0.0, 0.0]), [0.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    fn test_multiply() {
+        let translation = Transform { flags: Transform::HAS_POSITION, translation: [1.0, 0.0, 0.0], ..Transform::identity() };
+        let scale = Transform { flags: Transform::HAS_SCALE_SHEAR, scale_shear: [[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]], ..Transform::identity() };
+
+        let matrix = multiply_4x4(&translation.to_matrix(), &scale.to_matrix());
+        assert_near(transform_point(&matrix, [0.0, 0.0, 0.0]), [3.0, 0.0, 0.0]);
+        assert_eq!(multiply_4x4(&matrix, &IDENTITY_4X4), matrix);
+    }
+}
\ No newline at end of file
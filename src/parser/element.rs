@@ -1,16 +1,30 @@
-use nom::bytes::complete::{take, take_while};
+use std::borrow::Cow;
+use nom::bytes::complete::take;
 use nom::IResult;
 use nom::multi::count;
-use nom::number::complete::{f32, i32, u32, u64, u8};
+use nom::number::complete::{f32, i16, i32, i8, u16, u32, u64, u8};
 use nom::number::Endianness;
 use nom::sequence::tuple;
-use crate::parser::Pointer;
+use crate::error::GrannyError;
+use crate::parser::{Pointer, Reference};
 use crate::sector::Sector;
 
-#[derive(Debug, PartialEq)]
-pub struct Element {
-    pub name: String,
-    pub element: ElementType
+/// A member of an object, names and strings are borrowed from the loaded data if possible
+#[derive(Debug, Clone)]
+pub struct Element<'a> {
+    pub name: Cow<'a, str>,
+    pub element: ElementType<'a>,
+    /// The location of the member definition in the type tree, see [crate::GrannyFile::member_definition]
+    ///
+    /// It's `None` for elements which weren't loaded from a file.
+    pub type_ref: Option<Reference>
+}
+
+/// Elements are equal if their names and values are, the location of their type depends on the layout of the file
+impl PartialEq for Element<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.element == other.element
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -21,38 +35,130 @@ pub struct Transform {
     pub scale_shear: [[f32; 3]; 3]
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ElementType {
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementType<'a> {
     /// A list of elements
-    Reference(Vec<Element>),
-    ArrayOfReferences(Vec<Vec<Element>>),
-    /// A pointer to raw data
-    /// NOTE: This is currently not supported by this library
-    VariantReference,
+    Reference(Vec<Element<'a>>),
+    /// A list of elements which are stored inline in the parent element
+    Inline(Vec<Element<'a>>),
+    /// A list of items which are stored one after another, each item is a list of elements
+    ReferenceToArray(Vec<Vec<Element<'a>>>),
+    /// A list of references, each reference is a list of elements
+    ArrayOfReferences(Vec<Vec<Element<'a>>>),
+    /// A list of items with a type which is stored next to the data (e.g. `Vertices`), each item is a list of elements
+    ReferenceToVariantArray(Vec<Vec<Element<'a>>>),
+    /// A list of elements with a type which is stored next to the data
+    ///
+    /// Granny doesn't store names for struct types, so `type_name` is the name of the first
+    /// member which identifies well known types (e.g. `CurveDataHeader_DaIdentity`). It's `None`
+    /// if the variant is empty.
+    Variant { type_name: Option<Cow<'a, str>>, elements: Vec<Element<'a>> },
     /// A string
-    String(String),
+    String(Cow<'a, str>),
     /// A real value (aka float 32)
     F32(f32),
+    /// A half precision real value (aka float 16) converted to a float 32
+    F16(f32),
+    /// A signed 8 bit integer
+    I8(i8),
     /// An unsigned 8 bit integer
     U8(u8),
+    /// A signed 8 bit integer which represents a value from -1 to 1
+    BinormalI8(i8),
+    /// An unsigned 8 bit integer which represents a value from 0 to 1
+    NormalU8(u8),
+    /// A signed 16 bit integer
+    I16(i16),
+    /// An unsigned 16 bit integer
+    U16(u16),
+    /// A signed 16 bit integer which represents a value from -1 to 1
+    BinormalI16(i16),
+    /// An unsigned 16 bit integer which represents a value from 0 to 1
+    NormalU16(u16),
     /// A signed 32 bit integer
     I32(i32),
-    ///
+    /// An unsigned 32 bit integer
+    U32(u32),
+    /// A reference without a type which is always empty
+    EmptyReference,
+    /// A transform (flags, translation, rotation and scale/shear)
     Transform(Transform),
 
     /// Not really an element type and instead it's an array inside the element
-    Array(Vec<ElementType>)
+    Array(Vec<ElementType<'a>>)
+}
+
+fn into_owned_elements(elements: Vec<Element>) -> Vec<Element<'static>> {
+    elements.into_iter().map(Element::into_owned).collect()
+}
+
+impl Element<'_> {
+    /// Copies all borrowed names and strings, so the element outlives the loaded data
+    pub fn into_owned(self) -> Element<'static> {
+        Element {
+            name: Cow::Owned(self.name.into_owned()),
+            element: self.element.into_owned(),
+            type_ref: self.type_ref
+        }
+    }
 }
 
+impl ElementType<'_> {
+    /// Copies all borrowed names and strings, so the element outlives the loaded data
+    pub fn into_owned(self) -> ElementType<'static> {
+        match self {
+            ElementType::Reference(elements) => ElementType::Reference(into_owned_elements(elements)),
+            ElementType::Inline(elements) => ElementType::Inline(into_owned_elements(elements)),
+            ElementType::ReferenceToArray(items) => ElementType::ReferenceToArray(items.into_iter().map(into_owned_elements).collect()),
+            ElementType::ArrayOfReferences(items) => ElementType::ArrayOfReferences(items.into_iter().map(into_owned_elements).collect()),
+            ElementType::ReferenceToVariantArray(items) => ElementType::ReferenceToVariantArray(items.into_iter().map(into_owned_elements).collect()),
+            ElementType::Variant { type_name, elements } => ElementType::Variant {
+                type_name: type_name.map(|name| Cow::Owned(name.into_owned())),
+                elements: into_owned_elements(elements)
+            },
+            ElementType::String(value) => ElementType::String(Cow::Owned(value.into_owned())),
+            ElementType::F32(value) => ElementType::F32(value),
+            ElementType::F16(value) => ElementType::F16(value),
+            ElementType::I8(value) => ElementType::I8(value),
+            ElementType::U8(value) => ElementType::U8(value),
+            ElementType::I16(value) => ElementType::I16(value),
+            ElementType::U16(value) => ElementType::U16(value),
+            ElementType::BinormalI8(value) => ElementType::BinormalI8(value),
+            ElementType::NormalU8(value) => ElementType::NormalU8(value),
+            ElementType::BinormalI16(value) => ElementType::BinormalI16(value),
+            ElementType::NormalU16(value) => ElementType::NormalU16(value),
+            ElementType::I32(value) => ElementType::I32(value),
+            ElementType::U32(value) => ElementType::U32(value),
+            ElementType::EmptyReference => ElementType::EmptyReference,
+            ElementType::Transform(transform) => ElementType::Transform(transform),
+            ElementType::Array(values) => ElementType::Array(values.into_iter().map(ElementType::into_owned).collect())
+        }
+    }
+
+    /// Returns the value of a normalized integer as a float, `None` for all other elements
+    pub fn normalized(&self) -> Option<f32> {
+        match self {
+            ElementType::BinormalI8(value) => Some((*value as f32 / i8::MAX as f32).max(-1.0)),
+            ElementType::NormalU8(value) => Some(*value as f32 / u8::MAX as f32),
+            ElementType::BinormalI16(value) => Some((*value as f32 / i16::MAX as f32).max(-1.0)),
+            ElementType::NormalU16(value) => Some(*value as f32 / u16::MAX as f32),
+            _ => None
+        }
+    }
+}
+
+/// A member definition of a struct type as it's stored in the type tree
 #[derive(Debug, PartialEq)]
 pub struct TypeInfo {
-    type_id: u32,
-    name_offset: Option<Pointer>,
-    children_offset: Option<Pointer>,
-    array_size: i32,
+    pub type_id: u32,
+    pub name_offset: Option<Pointer>,
+    pub children_offset: Option<Pointer>,
+    pub array_size: i32,
+    /// Exporter specific values which Granny doesn't interpret
+    pub extra: [u32; 3]
 }
 
-fn unsigned(is_64bits: bool, endianness: Endianness) -> impl FnMut(&[u8]) -> IResult<&[u8], u64> {
+fn unsigned(is_64bits: bool, endianness: Endianness) -> impl FnMut(&[u8]) -> IResult<&[u8], u64, GrannyError> {
     move |input| {
         if is_64bits {
             u64(endianness)(input)
@@ -63,7 +169,42 @@ fn unsigned(is_64bits: bool, endianness: Endianness) -> impl FnMut(&[u8]) -> IRe
     }
 }
 
-pub fn parse_type_info(endianness: Endianness, type_sector: &Sector, is_64bits: bool, offset: u32) -> impl FnMut(&[u8]) -> IResult<&[u8], TypeInfo> + '_ {
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // Subnormal values get normalized for the float 32 representation
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x3FF) << 13
+        }
+        (0x1F, _) => sign | 0x7F800000 | mantissa << 13,
+        _ => sign | ((exponent + 112) << 23) | mantissa << 13
+    };
+
+    f32::from_bits(bits)
+}
+
+fn failure(error: GrannyError) -> nom::Err<GrannyError> {
+    nom::Err::Failure(error)
+}
+
+fn get_sector<'s, 'a>(sectors: &'s [Sector<'a>], sector: u32) -> Result<&'s Sector<'a>, nom::Err<GrannyError>> {
+    sectors.get(sector as usize).ok_or_else(|| failure(GrannyError::SectorOutOfRange(sector)))
+}
+
+fn get_sector_data<'s>(sectors: &'s [Sector], sector: u32, offset: u32) -> Result<&'s [u8], nom::Err<GrannyError>> {
+    get_sector(sectors, sector)?.data.get(offset as usize..).ok_or_else(|| failure(GrannyError::BadPointer { sector, offset }))
+}
+
+fn resolve_pointer(sector: &Sector, sector_id: u32, offset: usize) -> Result<Pointer, nom::Err<GrannyError>> {
+    sector.resolve_pointer(offset).ok_or_else(|| failure(GrannyError::BadPointer { sector: sector_id, offset: offset as u32 }))
+}
+
+pub fn parse_type_info<'s>(endianness: Endianness, type_sector: &'s Sector<'s>, is_64bits: bool, offset: u32) -> impl FnMut(&[u8]) -> IResult<&[u8], TypeInfo, GrannyError> + 's {
     move |input| {
         let type_id = u32(endianness); // 0
         let name_offset = unsigned(is_64bits, endianness); // 4
@@ -72,7 +213,8 @@ pub fn parse_type_info(endianness: Endianness, type_sector: &Sector, is_64bits:
 
         let (input, (type_id, _, _, array_size)) = tuple((type_id, name_offset, children_offset, array_size))(input)?;
 
-        let (input, _) = take(if is_64bits { 20usize } else { 16usize })(input)?;
+        let (input, extra) = count(u32(endianness), 3)(input)?;
+        let (input, _) = take(if is_64bits { 8usize } else { 4usize })(input)?;
 
         let name_offset = type_sector.resolve_pointer((offset + 4) as _);
         let children_offset = type_sector.resolve_pointer((offset + if is_64bits { 12 } else { 8 }) as _);
@@ -81,46 +223,68 @@ pub fn parse_type_info(endianness: Endianness, type_sector: &Sector, is_64bits:
             type_id,
             name_offset,
             children_offset,
-            array_size
+            array_size,
+            extra: [extra[0], extra[1], extra[2]]
         }))
     }
 }
 
-fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
-    let (input, bytes) = take_while(|n| { n != 0x0 })(input)?;
+fn parse_str(data: &[u8], sector: u32, offset: u32) -> Result<&str, nom::Err<GrannyError>> {
+    let data = data.get(offset as usize..).ok_or_else(|| failure(GrannyError::BadPointer { sector, offset }))?;
+    let bytes = data.split(|&b| b == 0).next().unwrap_or_default();
+
+    std::str::from_utf8(bytes).map_err(|_| failure(GrannyError::InvalidString))
+}
+
+/// Reads a zero terminated string, it's only copied if the sector was decompressed
+pub(super) fn parse_string<'a>(sectors: &[Sector<'a>], sector: u32, offset: u32) -> Result<Cow<'a, str>, nom::Err<GrannyError>> {
+    match &get_sector(sectors, sector)?.data {
+        Cow::Borrowed(data) => Ok(Cow::Borrowed(parse_str(data, sector, offset)?)),
+        Cow::Owned(data) => Ok(Cow::Owned(parse_str(data, sector, offset)?.to_string()))
+    }
+}
+
+/// The objects (data sector, data offset, type sector, type offset) which are currently parsed
+type Ancestors = Vec<[u32; 4]>;
 
-    Ok((input, std::str::from_utf8(bytes).expect("invalid string").to_string()))
+pub fn parse_element<'s, 'a>(endianness: Endianness, is_64bits: bool, sectors: &'s [Sector<'a>], data_sector_id: u32, type_sector_id: u32, data_offset: u32, type_offset: u32) -> IResult<&'s [u8], Vec<Element<'a>>, GrannyError> {
+    parse_object(endianness, is_64bits, sectors, (data_sector_id, data_offset), (type_sector_id, type_offset), &mut Vec::new())
 }
 
-pub fn parse_element(endianness: Endianness, is_64bits: bool, sectors: &Vec<Sector>, data_sector_id: u32, type_sector_id: u32, data_offset: u32, type_offset: u32) -> IResult<&[u8], Vec<Element>> {
-    let data_sector = &sectors[data_sector_id as usize];
-    let type_sector = &sectors[type_sector_id as usize];
+/// Parses the members of an object, an object which references one of its ancestors is reported as cyclic
+fn parse_object<'s, 'a>(endianness: Endianness, is_64bits: bool, sectors: &'s [Sector<'a>], (data_sector_id, data_offset): (u32, u32), (type_sector_id, type_offset): (u32, u32), ancestors: &mut Ancestors) -> IResult<&'s [u8], Vec<Element<'a>>, GrannyError> {
+    let object = [data_sector_id, data_offset, type_sector_id, type_offset];
+    if ancestors.contains(&object) {
+        return Err(failure(GrannyError::CyclicReference { sector: data_sector_id, offset: data_offset }))
+    }
+    ancestors.push(object);
 
+    let type_sector = get_sector(sectors, type_sector_id)?;
     let all_type_data = &*type_sector.data;
-    let all_data = &*data_sector.data;
 
-    let mut type_data = &all_type_data[type_offset as usize..];
-    let mut data = &all_data[data_offset as usize..];
+    let mut type_data = get_sector_data(sectors, type_sector_id, type_offset)?;
+    let mut data = get_sector_data(sectors, data_sector_id, data_offset)?;
 
     let mut elements = Vec::new();
 
     loop {
-        let (next, type_info) = parse_type_info(endianness, type_sector, is_64bits, (all_type_data.len() - type_data.len()) as u32)(type_data)?;
-        if type_info.type_id == 0 || type_info.type_id > 22 {
+        let type_position = (all_type_data.len() - type_data.len()) as u32;
+        let (next, type_info) = parse_type_info(endianness, type_sector, is_64bits, type_position)(type_data)?;
+        if type_info.type_id == 0 {
             break
         }
+        let type_ref = Some(Reference { sector: type_sector_id, position: type_position });
 
         let name = if let Some(name_offset) = type_info.name_offset {
-            let (_, name) = parse_string(&sectors[name_offset.dst_sector as usize].data[name_offset.dst_offset as usize..])?;
-            name
+            parse_string(sectors, name_offset.dst_sector, name_offset.dst_offset)?
         } else {
-            "".to_string()
+            Cow::Borrowed("")
         };
 
         let element = if type_info.array_size > 0 {
             let mut inners = Vec::new();
             for _ in 0..(if type_info.array_size == 0 { 1 } else { type_info.array_size }) {
-                let (next, element_inner) = parse_element_data(endianness, is_64bits, sectors, data_sector_id, all_data, data, &type_info)?;
+                let (next, element_inner) = parse_element_data(endianness, is_64bits, sectors, data_sector_id, data, &type_info, ancestors)?;
                 data = next;
 
                 inners.push(element_inner);
@@ -128,15 +292,17 @@ pub fn parse_element(endianness: Endianness, is_64bits: bool, sectors: &Vec<Sect
 
             Element {
                 name,
-                element: ElementType::Array(inners)
+                element: ElementType::Array(inners),
+                type_ref
             }
         } else {
-            let (next, element_inner) = parse_element_data(endianness, is_64bits, sectors, data_sector_id, all_data, data, &type_info)?;
+            let (next, element_inner) = parse_element_data(endianness, is_64bits, sectors, data_sector_id, data, &type_info, ancestors)?;
             data = next;
 
             Element {
                 name,
-                element: element_inner
+                element: element_inner,
+                type_ref
             }
         };
 
@@ -144,14 +310,35 @@ pub fn parse_element(endianness: Endianness, is_64bits: bool, sectors: &Vec<Sect
         type_data = next;
     }
 
+    ancestors.pop();
+
     Ok((data, elements))
 }
 
-fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a Vec<Sector>, data_sector_id: u32, all_data: &[u8], mut data: &'a [u8], type_info: &TypeInfo) -> IResult<&'a [u8], ElementType> {
-    let data_sector = &sectors[data_sector_id as usize];
+/// Returns the type of the children of a reference or inline member stored at `offset` in the data sector
+fn children_type(type_info: &TypeInfo, data_sector_id: u32, offset: usize) -> Result<Pointer, nom::Err<GrannyError>> {
+    type_info.children_offset.ok_or_else(|| failure(GrannyError::BadPointer { sector: data_sector_id, offset: offset as u32 }))
+}
+pub(super) fn parse_element_data<'s, 'a>(endianness: Endianness, is_64bits: bool, sectors: &'s [Sector<'a>], data_sector_id: u32, mut data: &'s [u8], type_info: &TypeInfo, ancestors: &mut Ancestors) -> IResult<&'s [u8], ElementType<'a>, GrannyError> {
+    let data_sector = get_sector(sectors, data_sector_id)?;
+    let all_data = &*data_sector.data;
     match type_info.type_id {
         1 => {
-            Ok((data, ElementType::VariantReference))
+            let pos = all_data.len() - data.len();
+            let children_offset = children_type(type_info, data_sector_id, pos)?;
+
+            let (next, elements) = parse_object(
+                endianness,
+                is_64bits,
+                sectors,
+                (data_sector_id, pos as u32),
+                (children_offset.dst_sector, children_offset.dst_offset),
+                ancestors
+            )?;
+
+            data = next;
+
+            Ok((data, ElementType::Inline(elements)))
         }
         2 => {
             let pos = all_data.len() - data.len();
@@ -161,18 +348,15 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
 
             let ptr = data_sector.resolve_pointer(pos);
             let elements = if let Some(ptr) = ptr {
-                assert_eq!(ptr.dst_sector, data_sector_id);
+                let children_offset = children_type(type_info, data_sector_id, pos)?;
 
-                let children_offset = type_info.children_offset.unwrap();
-
-                let (_, elements) = parse_element(
+                let (_, elements) = parse_object(
                     endianness,
                     is_64bits,
                     sectors,
-                    ptr.dst_sector,
-                    children_offset.dst_sector,
-                    ptr.dst_offset,
-                    children_offset.dst_offset
+                    (ptr.dst_sector, ptr.dst_offset),
+                    (children_offset.dst_sector, children_offset.dst_offset),
+                    ancestors
                 )?;
 
                 elements
@@ -190,26 +374,26 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
             let (next, (size, _)) = tuple((size, offset))(data)?;
             data = next;
 
-            let mut elements = Vec::new();
+            let mut items = Vec::new();
 
             let data_ptr = data_sector.resolve_pointer(pos);
             if size > 0 && data_ptr.is_some() {
                 if let Some(data_ptr) = data_ptr {
-                    let type_ptr = type_info.children_offset.unwrap();
+                    let type_ptr = children_type(type_info, data_sector_id, pos)?;
 
-                    let data_sector = &sectors[data_ptr.dst_sector as usize];
+                    let data_sector = get_sector(sectors, data_ptr.dst_sector)?;
 
                     let mut data_offset = data_ptr.dst_offset;
                     for _ in 0..size {
-                        let (left_data, mut e) = parse_element(endianness, is_64bits, sectors, data_ptr.dst_sector, type_ptr.dst_sector, data_offset, type_ptr.dst_offset)?;
-                        elements.append(&mut e);
+                        let (left_data, e) = parse_object(endianness, is_64bits, sectors, (data_ptr.dst_sector, data_offset), (type_ptr.dst_sector, type_ptr.dst_offset), ancestors)?;
+                        items.push(e);
 
                         data_offset = (data_sector.data.len() - left_data.len()) as u32;
                     }
                 }
             }
 
-            Ok((data, ElementType::Reference(elements)))
+            Ok((data, ElementType::ReferenceToArray(items)))
         }
         4 => {
             let pos = all_data.len() - data.len() + 4;
@@ -224,14 +408,24 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
             let mut references = Vec::new();
 
             if let Some(ptr) = ptr {
-                let type_ptr = type_info.children_offset.unwrap();
+                let type_ptr = children_type(type_info, data_sector_id, pos)?;
 
-                let element_data_sector = &sectors[ptr.dst_sector as usize];
+                let element_data_sector = get_sector(sectors, ptr.dst_sector)?;
 
-                for i in 0..size {
-                    let element_ptr = element_data_sector.resolve_pointer((ptr.dst_offset + if is_64bits { 8 * i } else { 4 * i }) as usize).unwrap();
+                for i in 0..size as usize {
+                    let element_pos = ptr.dst_offset as usize + if is_64bits { 8 * i } else { 4 * i };
+                    if element_pos >= element_data_sector.data.len() {
+                        return Err(failure(GrannyError::BadPointer { sector: ptr.dst_sector, offset: element_pos as u32 }));
+                    }
+
+                    let element_ptr = element_data_sector.resolve_pointer(element_pos);
 
-                    let (_, e) = parse_element(endianness, is_64bits, sectors, element_ptr.dst_sector, type_ptr.dst_sector, element_ptr.dst_offset, type_ptr.dst_offset)?;
+                    let e = if let Some(element_ptr) = element_ptr {
+                        let (_, e) = parse_object(endianness, is_64bits, sectors, (element_ptr.dst_sector, element_ptr.dst_offset), (type_ptr.dst_sector, type_ptr.dst_offset), ancestors)?;
+                        e
+                    } else {
+                        Vec::new()
+                    };
 
                     references.push(e);
                 }
@@ -240,13 +434,27 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
             Ok((data, ElementType::ArrayOfReferences(references)))
         }
         5 => {
-            let offset = unsigned(is_64bits, endianness);
+            let pos = all_data.len() - data.len();
+
+            let type_ptr = unsigned(is_64bits, endianness);
             let data_ptr = unsigned(is_64bits, endianness);
 
-            let (next, (_, _)) = tuple((offset, data_ptr))(data)?;
+            let (next, (_, _)) = tuple((type_ptr, data_ptr))(data)?;
             data = next;
 
-            Ok((data, ElementType::VariantReference))
+            let type_ptr = data_sector.resolve_pointer(pos);
+            let data_ptr = data_sector.resolve_pointer(pos + if is_64bits { 8 } else { 4 });
+
+            let (type_name, elements) = if let (Some(type_ptr), Some(data_ptr)) = (type_ptr, data_ptr) {
+                let (_, elements) = parse_object(endianness, is_64bits, sectors, (data_ptr.dst_sector, data_ptr.dst_offset), (type_ptr.dst_sector, type_ptr.dst_offset), ancestors)?;
+                let type_name = elements.first().map(|e| e.name.clone()).unwrap_or_default();
+
+                (Some(type_name), elements)
+            } else {
+                (None, Vec::new())
+            };
+
+            Ok((data, ElementType::Variant { type_name, elements }))
         }
         7 => {
             let pos = all_data.len() - data.len();
@@ -258,22 +466,26 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
             let (next, (_, size, _)) = tuple((type_ptr, size, data_ptr))(data)?;
             data = next;
 
-            let type_ptr = data_sector.resolve_pointer(pos).unwrap();
-            let data_ptr = data_sector.resolve_pointer(pos + if is_64bits { 8 + 4 } else { 4 + 4 }).unwrap();
+            let mut elements = Vec::new();
 
-            let mut data_offset = data_ptr.dst_offset;
+            if size > 0 {
+                let type_ptr = resolve_pointer(data_sector, data_sector_id, pos)?;
+                let data_ptr = resolve_pointer(data_sector, data_sector_id, pos + if is_64bits { 8 + 4 } else { 4 + 4 })?;
 
-            let mut elements = Vec::new();
+                let element_data_sector = get_sector(sectors, data_ptr.dst_sector)?;
 
-            for _ in 0..size {
-                let (left_data, e) = parse_element(endianness, is_64bits, sectors, data_ptr.dst_sector, type_ptr.dst_sector, data_offset, type_ptr.dst_offset)?;
+                let mut data_offset = data_ptr.dst_offset;
 
-                data_offset = (sectors[data_ptr.dst_sector as usize].data.len() - left_data.len()) as _;
+                for _ in 0..size {
+                    let (left_data, e) = parse_object(endianness, is_64bits, sectors, (data_ptr.dst_sector, data_offset), (type_ptr.dst_sector, type_ptr.dst_offset), ancestors)?;
 
-                elements.push(e);
+                    data_offset = (element_data_sector.data.len() - left_data.len()) as _;
+
+                    elements.push(e);
+                }
             }
 
-            Ok((data, ElementType::ArrayOfReferences(elements)))
+            Ok((data, ElementType::ReferenceToVariantArray(elements)))
         }
         8 => {
             let pos = all_data.len() - data.len();
@@ -281,8 +493,11 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
 
             data = next;
 
-            let ptr = data_sector.resolve_pointer(pos).unwrap();
-            let (_, value) = parse_string(&sectors[ptr.dst_sector as usize].data[ptr.dst_offset as usize..])?;
+            let value = if let Some(ptr) = data_sector.resolve_pointer(pos) {
+                parse_string(sectors, ptr.dst_sector, ptr.dst_offset)?
+            } else {
+                Cow::Borrowed("")
+            };
 
             Ok((data, ElementType::String(value)))
         }
@@ -312,20 +527,98 @@ fn parse_element_data<'a>(endianness: Endianness, is_64bits: bool, sectors: &'a
 
             Ok((data, ElementType::F32(val)))
         },
-        12 | 14 => {
+        11 => {
+            let (next, val) = i8(data)?;
+            data = next;
+
+            Ok((data, ElementType::I8(val)))
+        }
+        12 => {
             let (next, val) = u8(data)?;
             data = next;
 
             Ok((data, ElementType::U8(val)))
         }
+        13 => {
+            let (next, val) = i8(data)?;
+            data = next;
+
+            Ok((data, ElementType::BinormalI8(val)))
+        }
+        14 => {
+            let (next, val) = u8(data)?;
+            data = next;
+
+            Ok((data, ElementType::NormalU8(val)))
+        }
+        15 => {
+            let (next, val) = i16(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::I16(val)))
+        }
+        16 => {
+            let (next, val) = u16(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::U16(val)))
+        }
+        17 => {
+            let (next, val) = i16(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::BinormalI16(val)))
+        }
+        18 => {
+            let (next, val) = u16(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::NormalU16(val)))
+        }
         19 => {
             let (next, val) = i32(endianness)(data)?;
             data = next;
 
             Ok((data, ElementType::I32(val)))
         }
+        20 => {
+            let (next, val) = u32(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::U32(val)))
+        }
+        21 => {
+            let (next, val) = u16(endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::F16(f16_to_f32(val))))
+        }
+        22 => {
+            let (next, _) = unsigned(is_64bits, endianness)(data)?;
+            data = next;
+
+            Ok((data, ElementType::EmptyReference))
+        }
         _ => {
-            panic!("Unknown element type id {}", type_info.type_id);
+            Err(failure(GrannyError::UnknownMemberType(type_info.type_id)))
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::element::f16_to_f32;
+
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3C00), 1.0);
+        assert_eq!(f16_to_f32(0xC000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.33325195);
+        assert_eq!(f16_to_f32(0x7BFF), 65504.0);
+        assert_eq!(f16_to_f32(0x0001), 5.9604645e-8);
+        assert_eq!(f16_to_f32(0x03FF), 6.097555e-5);
+        assert_eq!(f16_to_f32(0x7C00), f32::INFINITY);
+        assert!(f16_to_f32(0x7E00).is_nan());
+    }
 }
\ No newline at end of file
@@ -32,7 +32,7 @@ pub fn parse_file_info(endianness: Endianness) -> impl FnMut(&[u8]) -> IResult<&
             (format, total_size, crc32, file_info_size, sector_count, type_ref, root_ref, tag)
         )(input)?;
 
-        let (input, _) = take((file_info_size - 40) as usize)(input)?;
+        let (input, _) = take(file_info_size.saturating_sub(40) as usize)(input)?;
 
         Ok((input, FileInfo {
             format_version,
@@ -48,6 +48,7 @@ pub fn parse_file_info(endianness: Endianness) -> impl FnMut(&[u8]) -> IResult<&
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     mod le_format_7_32bits {
         use nom::number::Endianness;
@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use crate::error::GrannyError;
+use crate::granny_path::split_path;
+use super::lazy::{Location, parse_members};
+use super::{ElementType, LazyElement, LazyElementType, LazyObject};
+
+/// Index of an object in [ObjectGraph::objects]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectId(pub usize);
+
+/// A member of an object in the graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphElement<'a> {
+    pub name: Cow<'a, str>,
+    pub element: GraphElementType<'a>
+}
+
+/// Like [ElementType], but referenced objects are shared nodes of the graph
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphElementType<'a> {
+    /// A referenced object, `None` for empty references
+    Reference(Option<ObjectId>),
+    /// The members of an object which is stored inline in the parent object
+    Inline(Vec<GraphElement<'a>>),
+    /// Objects which are stored one after another
+    ReferenceToArray(Vec<ObjectId>),
+    /// A list of references
+    ArrayOfReferences(Vec<Option<ObjectId>>),
+    /// Objects with a type which is stored next to the data, which are stored one after another
+    ReferenceToVariantArray(Vec<ObjectId>),
+    /// An object with a type which is stored next to the data, see [ElementType::Variant]
+    Variant { type_name: Option<Cow<'a, str>>, object: Option<ObjectId> },
+    /// A value without any references (e.g. a string or number)
+    Value(ElementType<'a>),
+    /// An array inside the object
+    Array(Vec<GraphElementType<'a>>)
+}
+
+/// An object which is identified by the location of its data
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphObject<'a> {
+    pub sector: u32,
+    pub offset: u32,
+    pub members: Vec<GraphElement<'a>>
+}
+
+/// All objects of a file, each object is parsed once no matter how often it's referenced
+///
+/// Unlike the element tree, the graph can contain cycles (e.g. a skeleton which references its model).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectGraph<'a> {
+    pub objects: Vec<GraphObject<'a>>,
+    pub root: ObjectId
+}
+
+fn collect_references(element: &GraphElementType, references: &mut Vec<ObjectId>) {
+    match element {
+        GraphElementType::Reference(object) | GraphElementType::Variant { object, .. } => references.extend(object),
+        GraphElementType::Inline(members) => members.iter().for_each(|member| collect_references(&member.element, references)),
+        GraphElementType::ReferenceToArray(objects) | GraphElementType::ReferenceToVariantArray(objects) => references.extend(objects),
+        GraphElementType::ArrayOfReferences(objects) => references.extend(objects.iter().flatten()),
+        GraphElementType::Value(_) => {}
+        GraphElementType::Array(values) => values.iter().for_each(|value| collect_references(value, references))
+    }
+}
+
+impl GraphObject<'_> {
+    /// Returns the objects which are referenced by the members, in order of the members
+    pub fn references(&self) -> Vec<ObjectId> {
+        let mut references = Vec::new();
+        for member in &self.members {
+            collect_references(&member.element, &mut references);
+        }
+
+        references
+    }
+}
+
+impl<'a> ObjectGraph<'a> {
+    pub fn object(&self, id: ObjectId) -> &GraphObject<'a> {
+        &self.objects[id.0]
+    }
+
+    /// Returns the first object with its data at the given location
+    pub fn find_object(&self, sector: u32, offset: u32) -> Option<ObjectId> {
+        self.objects.iter().position(|object| object.sector == sector && object.offset == offset).map(ObjectId)
+    }
+
+    /// Returns how often each object is referenced, shared objects are referenced more than once
+    pub fn reference_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.objects.len()];
+        for object in &self.objects {
+            for ObjectId(id) in object.references() {
+                counts[id] += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Returns the objects of a cycle, each object references the next one and the last one the first
+    pub fn find_cycle(&self) -> Option<Vec<ObjectId>> {
+        const NEW: u8 = 0;
+        const VISITING: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut states = vec![NEW; self.objects.len()];
+
+        for start in 0..self.objects.len() {
+            if states[start] != NEW {
+                continue
+            }
+
+            states[start] = VISITING;
+            let mut stack = vec![(ObjectId(start), self.objects[start].references(), 0)];
+
+            while let Some((id, references, next)) = stack.last_mut() {
+                let Some(&reference) = references.get(*next) else {
+                    states[id.0] = DONE;
+                    stack.pop();
+                    continue
+                };
+                *next += 1;
+
+                match states[reference.0] {
+                    NEW => {
+                        states[reference.0] = VISITING;
+                        stack.push((reference, self.object(reference).references(), 0));
+                    }
+                    VISITING => {
+                        let position = stack.iter().position(|(id, _, _)| *id == reference).unwrap_or_default();
+                        return Some(stack[position..].iter().map(|(id, _, _)| *id).collect())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Looks up a member by a path like [crate::GrannyResolve], starting at the root object
+    pub fn find_element(&self, path: &str) -> Option<&GraphElement<'a>> {
+        self.resolve_in(&self.object(self.root).members, path)
+    }
+
+    fn resolve_in<'s>(&'s self, elements: &'s [GraphElement<'a>], path: &str) -> Option<&'s GraphElement<'a>> {
+        let (name, index, rest) = split_path(path)?;
+        let e = elements.iter().find(|e| e.name == name)?;
+
+        match (index, rest) {
+            (None, None) => Some(e),
+            (None, Some(rest)) => match &e.element {
+                GraphElementType::Reference(Some(object)) | GraphElementType::Variant { object: Some(object), .. } => {
+                    self.resolve_in(&self.object(*object).members, rest)
+                }
+                GraphElementType::Inline(members) => self.resolve_in(members, rest),
+                _ => None
+            },
+            (Some(index), Some(rest)) => match &e.element {
+                GraphElementType::ReferenceToArray(objects) | GraphElementType::ReferenceToVariantArray(objects) => self.resolve_in(&self.object(*objects.get(index)?).members, rest),
+                GraphElementType::ArrayOfReferences(objects) => self.resolve_in(&self.object((*objects.get(index)?)?).members, rest),
+                _ => None
+            },
+            (Some(_), None) => None
+        }
+    }
+}
+
+/// Parses every object which is reachable from the root once
+struct Builder<'a> {
+    objects: Vec<GraphObject<'a>>,
+    ids: HashMap<(Location, Location), ObjectId>,
+    pending: Vec<(ObjectId, Location, Location)>
+}
+
+impl<'a> Builder<'a> {
+    /// Returns the id of an object and queues it for parsing when it's seen the first time
+    fn id(&mut self, object: &LazyObject<'a>) -> Option<ObjectId> {
+        let (data, type_ref) = object.location?;
+
+        Some(*self.ids.entry((data, type_ref)).or_insert_with(|| {
+            let id = ObjectId(self.objects.len());
+            self.objects.push(GraphObject { sector: data.0, offset: data.1, members: Vec::new() });
+            self.pending.push((id, data, type_ref));
+
+            id
+        }))
+    }
+
+    fn convert_members(&mut self, members: Vec<LazyElement<'a>>) -> Result<Vec<GraphElement<'a>>, GrannyError> {
+        let mut elements = Vec::with_capacity(members.len());
+        for member in members {
+            elements.push(GraphElement { name: member.name, element: self.convert(member.element)? });
+        }
+
+        Ok(elements)
+    }
+
+    fn convert(&mut self, element: LazyElementType<'a>) -> Result<GraphElementType<'a>, GrannyError> {
+        Ok(match element {
+            LazyElementType::Reference(object) => GraphElementType::Reference(self.id(&object)),
+            LazyElementType::Inline(members) => GraphElementType::Inline(self.convert_members(members)?),
+            LazyElementType::ReferenceToArray(array) => {
+                GraphElementType::ReferenceToArray(array.items()?.iter().filter_map(|object| self.id(object)).collect())
+            }
+            LazyElementType::ArrayOfReferences(array) => {
+                GraphElementType::ArrayOfReferences(array.items()?.iter().map(|object| self.id(object)).collect())
+            }
+            LazyElementType::ReferenceToVariantArray(array) => {
+                GraphElementType::ReferenceToVariantArray(array.items()?.iter().filter_map(|object| self.id(object)).collect())
+            }
+            LazyElementType::Variant { type_name, object } => GraphElementType::Variant { type_name, object: self.id(&object) },
+            LazyElementType::Value(value) => GraphElementType::Value(value),
+            LazyElementType::Array(values) => {
+                let mut converted = Vec::with_capacity(values.len());
+                for value in values {
+                    converted.push(self.convert(value)?);
+                }
+
+                GraphElementType::Array(converted)
+            }
+        })
+    }
+}
+
+impl<'a> ObjectGraph<'a> {
+    /// Parses all objects which are reachable from `root`
+    pub fn from_root(root: &LazyObject<'a>) -> Result<ObjectGraph<'a>, GrannyError> {
+        let mut builder = Builder { objects: Vec::new(), ids: HashMap::new(), pending: Vec::new() };
+        let root_id = builder.id(root).unwrap_or(ObjectId(0));
+        if root.is_null() {
+            builder.objects.push(GraphObject { sector: 0, offset: 0, members: Vec::new() });
+        }
+
+        while let Some((id, data, type_ref)) = builder.pending.pop() {
+            let (members, _) = parse_members(&root.context, data, type_ref)?;
+            builder.objects[id.0].members = builder.convert_members(members)?;
+        }
+
+        Ok(ObjectGraph { objects: builder.objects, root: root_id })
+    }
+}
\ No newline at end of file
@@ -53,6 +53,7 @@ pub fn parse_header(input: &[u8]) -> IResult<&[u8], Header> {
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     mod le_format_7_32bits {
         use crate::parser::{Header, parse_header};
@@ -0,0 +1,539 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use nom::number::complete::u32;
+use nom::number::Endianness;
+use crate::error::GrannyError;
+use crate::granny_path::resolve_lazy;
+use crate::sector::{native_sector_data, Sector};
+use super::element::{parse_element_data, parse_string};
+use super::{Element, ElementType, parse_element, parse_type_info, Reference, TypeDefinition, TypeInfo, TypeTree};
+
+/// A location (sector, offset) in the data or type tree
+pub(super) type Location = (u32, u32);
+
+/// The sectors of a file which objects are parsed from on demand
+pub(crate) struct Context<'a> {
+    pub(crate) endianness: Endianness,
+    pub(crate) is_64bits: bool,
+    pub(crate) sectors: Vec<Sector<'a>>,
+    /// The sector data in native byte order, only filled for files which need to be byte swapped
+    native_sectors: Vec<OnceCell<Vec<u8>>>
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(endianness: Endianness, is_64bits: bool, sectors: Vec<Sector<'a>>) -> Context<'a> {
+        let native_sectors = sectors.iter().map(|_| OnceCell::new()).collect();
+
+        Context { endianness, is_64bits, sectors, native_sectors }
+    }
+
+    /// Returns if the file doesn't use the byte order of the current platform
+    pub(super) fn is_swapped(&self) -> bool {
+        (self.endianness == Endianness::Big) != cfg!(target_endian = "big")
+    }
+
+    /// Returns the data of a sector in the byte order of the current platform, swapped sectors are cached
+    pub(super) fn native_sector(&self, sector: u32) -> Result<&[u8], GrannyError> {
+        if !self.is_swapped() {
+            return Ok(&self.sector(sector)?.data)
+        }
+
+        let native = self.native_sectors.get(sector as usize).ok_or(GrannyError::SectorOutOfRange(sector))?;
+        if let Some(data) = native.get() {
+            return Ok(data)
+        }
+
+        let data = native_sector_data(&self.sectors, sector, self.endianness, self.is_64bits)?;
+        Ok(native.get_or_init(|| data))
+    }
+    pub(super) fn pointer_size(&self) -> u32 {
+        if self.is_64bits { 8 } else { 4 }
+    }
+
+    pub(super) fn sector(&self, sector: u32) -> Result<&Sector<'a>, GrannyError> {
+        self.sectors.get(sector as usize).ok_or(GrannyError::SectorOutOfRange(sector))
+    }
+
+    fn pointer(&self, sector: u32, offset: u32) -> Result<Option<Location>, GrannyError> {
+        Ok(self.sector(sector)?.resolve_pointer(offset as usize).map(|pointer| (pointer.dst_sector, pointer.dst_offset)))
+    }
+
+    fn read_u32(&self, sector: u32, offset: u32) -> Result<u32, GrannyError> {
+        let data = self.sector(sector)?.data.get(offset as usize..).ok_or(GrannyError::BadPointer { sector, offset })?;
+        let (_, value) = u32::<_, GrannyError>(self.endianness)(data)?;
+
+        Ok(value)
+    }
+
+    /// Returns the size of a member definition in the type tree
+    pub(super) fn definition_size(&self) -> u32 {
+        4 + self.pointer_size() * 2 + 4 + if self.is_64bits { 20 } else { 16 }
+    }
+
+    /// Parses the member definitions of a struct up to its end marker
+    pub(super) fn type_infos(&self, (sector, offset): Location) -> Result<Vec<TypeInfo>, GrannyError> {
+        let types = self.sector(sector)?;
+
+        let mut type_infos = Vec::new();
+        for position in (offset..).step_by(self.definition_size() as usize) {
+            let input = types.data.get(position as usize..).ok_or(GrannyError::BadPointer { sector, offset: position })?;
+            let (_, type_info) = parse_type_info(self.endianness, types, self.is_64bits, position)(input)?;
+            if type_info.type_id == 0 {
+                break
+            }
+
+            type_infos.push(type_info);
+        }
+
+        Ok(type_infos)
+    }
+
+    pub(super) fn name(&self, type_info: &TypeInfo) -> Result<Cow<'a, str>, GrannyError> {
+        match type_info.name_offset {
+            Some(name) => Ok(parse_string(&self.sectors, name.dst_sector, name.dst_offset)?),
+            None => Ok(Cow::Borrowed(""))
+        }
+    }
+
+    /// Returns the size of a single value of a member, `location` is reported if an inline member has no type
+    pub(super) fn member_size(&self, type_info: &TypeInfo, location: Location) -> Result<u32, GrannyError> {
+        self.inline_member_size(type_info, location, &mut Vec::new())
+    }
+
+    /// Returns the size of a struct, members are stored without padding
+    pub(super) fn struct_size(&self, type_ref: Location) -> Result<u32, GrannyError> {
+        self.inline_struct_size(type_ref, &mut Vec::new())
+    }
+
+    /// Like [Context::member_size], `ancestors` are the types the member is inlined into
+    fn inline_member_size(&self, type_info: &TypeInfo, location: Location, ancestors: &mut Vec<Location>) -> Result<u32, GrannyError> {
+        let pointer_size = self.pointer_size();
+
+        Ok(match type_info.type_id {
+            1 => self.inline_struct_size(children(type_info, location)?, ancestors)?,
+            2 | 8 | 22 => pointer_size,
+            3 | 4 => 4 + pointer_size,
+            5 => pointer_size * 2,
+            7 => pointer_size * 2 + 4,
+            9 => 68,
+            10 | 19 | 20 => 4,
+            11..=14 => 1,
+            15..=18 | 21 => 2,
+            type_id => return Err(GrannyError::UnknownMemberType(type_id))
+        })
+    }
+
+    /// Like [Context::struct_size], a type which inlines itself is reported as cyclic
+    fn inline_struct_size(&self, type_ref: Location, ancestors: &mut Vec<Location>) -> Result<u32, GrannyError> {
+        if ancestors.contains(&type_ref) {
+            return Err(GrannyError::CyclicReference { sector: type_ref.0, offset: type_ref.1 })
+        }
+        ancestors.push(type_ref);
+
+        let mut size = 0u32;
+        for type_info in self.type_infos(type_ref)? {
+            size = self.inline_member_size(&type_info, type_ref, ancestors)?
+                .checked_mul(type_info.array_size.max(1) as u32)
+                .and_then(|member_size| size.checked_add(member_size))
+                .ok_or(GrannyError::TruncatedData)?;
+        }
+
+        ancestors.pop();
+        Ok(size)
+    }
+}
+
+/// Returns the type of the children of a member stored at `location`
+fn children(type_info: &TypeInfo, (sector, offset): Location) -> Result<Location, GrannyError> {
+    type_info.children_offset
+        .map(|pointer| (pointer.dst_sector, pointer.dst_offset))
+        .ok_or(GrannyError::BadPointer { sector, offset })
+}
+
+pub(super) fn parse_members<'a>(context: &Rc<Context<'a>>, (sector, mut offset): Location, type_ref: Location) -> Result<(Vec<LazyElement<'a>>, u32), GrannyError> {
+    let mut elements = Vec::new();
+
+    for (index, type_info) in context.type_infos(type_ref)?.into_iter().enumerate() {
+        let name = context.name(&type_info)?;
+        let definition = Reference { sector: type_ref.0, position: type_ref.1 + index as u32 * context.definition_size() };
+
+        let element = if type_info.array_size > 0 {
+            let mut values = Vec::new();
+            for _ in 0..type_info.array_size {
+                let (value, next) = parse_member(context, (sector, offset), &type_info)?;
+                values.push(value);
+                offset = next;
+            }
+
+            LazyElementType::Array(values)
+        } else {
+            let (value, next) = parse_member(context, (sector, offset), &type_info)?;
+            offset = next;
+
+            value
+        };
+
+        elements.push(LazyElement { name, element, type_ref: definition });
+    }
+
+    Ok((elements, offset))
+}
+
+/// Parses a single value of a member and returns it with the offset after it
+fn parse_member<'a>(context: &Rc<Context<'a>>, (sector, offset): Location, type_info: &TypeInfo) -> Result<(LazyElementType<'a>, u32), GrannyError> {
+    let pointer_size = context.pointer_size();
+    let data = &context.sector(sector)?.data;
+
+    let end = offset.checked_add(context.member_size(type_info, (sector, offset))?).ok_or(GrannyError::TruncatedData)?;
+    if end as usize > data.len() {
+        return Err(GrannyError::TruncatedData)
+    }
+
+    let element = match type_info.type_id {
+        1 => {
+            let (members, end) = parse_members(context, (sector, offset), children(type_info, (sector, offset))?)?;
+            return Ok((LazyElementType::Inline(members), end))
+        }
+        2 => {
+            let object = match context.pointer(sector, offset)? {
+                Some(data) => LazyObject::new(context, data, children(type_info, (sector, offset))?),
+                None => LazyObject::null(context)
+            };
+
+            LazyElementType::Reference(object)
+        }
+        3 | 4 => {
+            let layout = if type_info.type_id == 3 { Layout::Contiguous } else { Layout::References };
+            let count = context.read_u32(sector, offset)?;
+
+            let array = match context.pointer(sector, offset + 4)? {
+                Some(data) if count > 0 => LazyArray::new(context, layout, count, data, children(type_info, (sector, offset + 4))?),
+                _ => LazyArray::empty(context)
+            };
+
+            if layout == Layout::Contiguous {
+                LazyElementType::ReferenceToArray(array)
+            } else {
+                LazyElementType::ArrayOfReferences(array)
+            }
+        }
+        5 => {
+            let type_ref = context.pointer(sector, offset)?;
+            let data = context.pointer(sector, offset + pointer_size)?;
+
+            match (type_ref, data) {
+                (Some(type_ref), Some(data)) => {
+                    // Like the element tree, the type is named after its first member
+                    let type_name = match context.type_infos(type_ref)?.first() {
+                        Some(type_info) => context.name(type_info)?,
+                        None => Cow::Borrowed("")
+                    };
+
+                    LazyElementType::Variant { type_name: Some(type_name), object: LazyObject::new(context, data, type_ref) }
+                }
+                _ => LazyElementType::Variant { type_name: None, object: LazyObject::null(context) }
+            }
+        }
+        7 => {
+            let count = context.read_u32(sector, offset + pointer_size)?;
+
+            let array = if count > 0 {
+                let type_ref = context.pointer(sector, offset)?.ok_or(GrannyError::BadPointer { sector, offset })?;
+                let data_offset = offset + pointer_size + 4;
+                let data = context.pointer(sector, data_offset)?.ok_or(GrannyError::BadPointer { sector, offset: data_offset })?;
+
+                LazyArray::new(context, Layout::Contiguous, count, data, type_ref)
+            } else {
+                LazyArray::empty(context)
+            };
+
+            LazyElementType::ReferenceToVariantArray(array)
+        }
+        _ => {
+            let (_, value) = parse_element_data(context.endianness, context.is_64bits, &context.sectors, sector, &data[offset as usize..], type_info, &mut Vec::new())?;
+
+            LazyElementType::Value(value)
+        }
+    };
+
+    Ok((element, end))
+}
+
+/// A member of a lazily parsed object
+#[derive(Debug)]
+pub struct LazyElement<'a> {
+    pub name: Cow<'a, str>,
+    pub element: LazyElementType<'a>,
+    /// The location of the member definition in the type tree, like [Element::type_ref]
+    pub type_ref: Reference
+}
+
+/// Like [ElementType], but references and arrays are parsed when they are first accessed
+#[derive(Debug)]
+pub enum LazyElementType<'a> {
+    /// A referenced object, it's null if the reference is empty
+    Reference(LazyObject<'a>),
+    /// The members of an object which is stored inline in the parent object
+    Inline(Vec<LazyElement<'a>>),
+    /// Objects which are stored one after another
+    ReferenceToArray(LazyArray<'a>),
+    /// A list of references
+    ArrayOfReferences(LazyArray<'a>),
+    /// Objects with a type which is stored next to the data, which are stored one after another
+    ReferenceToVariantArray(LazyArray<'a>),
+    /// An object with a type which is stored next to the data, see [ElementType::Variant]
+    Variant { type_name: Option<Cow<'a, str>>, object: LazyObject<'a> },
+    /// A value without any references (e.g. a string or number)
+    Value(ElementType<'a>),
+    /// An array inside the object
+    Array(Vec<LazyElementType<'a>>)
+}
+
+impl<'a> LazyElement<'a> {
+    /// Returns the array of `ReferenceToArray`, `ArrayOfReferences` and `ReferenceToVariantArray` members
+    pub fn array(&self) -> Option<&LazyArray<'a>> {
+        match &self.element {
+            LazyElementType::ReferenceToArray(array) | LazyElementType::ArrayOfReferences(array) | LazyElementType::ReferenceToVariantArray(array) => Some(array),
+            _ => None
+        }
+    }
+
+    /// Parses the whole element
+    pub fn to_element(&self) -> Result<Element<'a>, GrannyError> {
+        Ok(Element {
+            name: self.name.clone(),
+            element: self.element.to_element_type()?,
+            type_ref: Some(self.type_ref)
+        })
+    }
+}
+
+impl<'a> LazyElementType<'a> {
+    /// Parses the whole element and everything it references
+    pub fn to_element_type(&self) -> Result<ElementType<'a>, GrannyError> {
+        Ok(match self {
+            LazyElementType::Reference(object) => ElementType::Reference(object.to_elements()?),
+            LazyElementType::Inline(members) => ElementType::Inline(members.iter().map(LazyElement::to_element).collect::<Result<_, _>>()?),
+            LazyElementType::ReferenceToArray(array) => ElementType::ReferenceToArray(array.to_items()?),
+            LazyElementType::ArrayOfReferences(array) => ElementType::ArrayOfReferences(array.to_items()?),
+            LazyElementType::ReferenceToVariantArray(array) => ElementType::ReferenceToVariantArray(array.to_items()?),
+            LazyElementType::Variant { type_name, object } => ElementType::Variant { type_name: type_name.clone(), elements: object.to_elements()? },
+            LazyElementType::Value(value) => value.clone(),
+            LazyElementType::Array(values) => ElementType::Array(values.iter().map(LazyElementType::to_element_type).collect::<Result<_, _>>()?)
+        })
+    }
+}
+
+/// An object whose members are parsed when they are first accessed
+pub struct LazyObject<'a> {
+    pub(super) context: Rc<Context<'a>>,
+    /// The data and type of the object, `None` for empty references
+    pub(super) location: Option<(Location, Location)>,
+    members: OnceCell<Vec<LazyElement<'a>>>
+}
+
+impl<'a> LazyObject<'a> {
+    pub(crate) fn new(context: &Rc<Context<'a>>, data: Location, type_ref: Location) -> LazyObject<'a> {
+        LazyObject {
+            context: context.clone(),
+            location: Some((data, type_ref)),
+            members: OnceCell::new()
+        }
+    }
+
+    fn null(context: &Rc<Context<'a>>) -> LazyObject<'a> {
+        LazyObject {
+            context: context.clone(),
+            location: None,
+            members: OnceCell::new()
+        }
+    }
+
+    /// Returns the sector and offset of the object's data, `None` for empty references
+    pub fn location(&self) -> Option<(u32, u32)> {
+        self.location.map(|(data, _)| data)
+    }
+
+    /// Returns the location of the object's type in the type tree, `None` for empty references
+    pub fn type_ref(&self) -> Option<Reference> {
+        self.location.map(|(_, (sector, position))| Reference { sector, position })
+    }
+
+    /// Parses the definition of the object's type, which also works for the types of variants
+    pub fn type_definition(&self) -> Result<Option<TypeDefinition<'a>>, GrannyError> {
+        self.type_ref().map(|type_ref| TypeDefinition::load(&self.context, type_ref)).transpose()
+    }
+
+    /// Parses the object's type and all types which are reachable from it
+    pub fn type_tree(&self) -> Result<Option<TypeTree<'a>>, GrannyError> {
+        self.type_ref().map(|type_ref| TypeTree::load(&self.context, type_ref)).transpose()
+    }
+
+    pub(crate) fn context(&self) -> &Context<'a> {
+        &self.context
+    }
+
+    /// Returns `true` for empty references
+    pub fn is_null(&self) -> bool {
+        self.location.is_none()
+    }
+
+    /// Returns `true` once the members have been parsed
+    pub fn is_parsed(&self) -> bool {
+        self.members.get().is_some()
+    }
+
+    /// Parses the members on first access, referenced objects are parsed when they are accessed
+    pub fn members(&self) -> Result<&[LazyElement<'a>], GrannyError> {
+        if let Some(members) = self.members.get() {
+            return Ok(members)
+        }
+
+        let members = match self.location {
+            Some((data, type_ref)) => parse_members(&self.context, data, type_ref)?.0,
+            None => Vec::new()
+        };
+
+        Ok(self.members.get_or_init(|| members))
+    }
+
+    /// Parses the object and everything it references into elements
+    pub fn to_elements(&self) -> Result<Vec<Element<'a>>, GrannyError> {
+        let Some(((data_sector, data_offset), (type_sector, type_offset))) = self.location else {
+            return Ok(Vec::new())
+        };
+
+        let context = &self.context;
+        let (_, elements) = parse_element(context.endianness, context.is_64bits, &context.sectors, data_sector, type_sector, data_offset, type_offset)?;
+
+        Ok(elements)
+    }
+
+    /// Looks up a member by a path like [crate::GrannyResolve], parsing only the objects along the path
+    pub fn try_resolve(&self, path: &str) -> Result<Option<&LazyElement<'a>>, GrannyError> {
+        resolve_lazy(self.members()?, path)
+    }
+}
+
+impl Debug for LazyObject<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyObject")
+            .field("location", &self.location)
+            .field("members", &self.members.get())
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) enum Layout {
+    /// The objects are stored one after another
+    Contiguous,
+    /// Pointers to the objects are stored one after another
+    References
+}
+
+/// The objects of an array, which are created on first access
+pub struct LazyArray<'a> {
+    pub(super) context: Rc<Context<'a>>,
+    pub(super) layout: Layout,
+    pub(super) len: u32,
+    pub(super) data: Location,
+    pub(super) type_ref: Location,
+    items: OnceCell<Vec<LazyObject<'a>>>
+}
+
+impl<'a> LazyArray<'a> {
+    fn new(context: &Rc<Context<'a>>, layout: Layout, len: u32, data: Location, type_ref: Location) -> LazyArray<'a> {
+        LazyArray {
+            context: context.clone(),
+            layout,
+            len,
+            data,
+            type_ref,
+            items: OnceCell::new()
+        }
+    }
+
+    fn empty(context: &Rc<Context<'a>>) -> LazyArray<'a> {
+        LazyArray::new(context, Layout::Contiguous, 0, (0, 0), (0, 0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the location of the items' type in the type tree, `None` for empty arrays
+    pub fn type_ref(&self) -> Option<Reference> {
+        let (sector, position) = self.type_ref;
+        (!self.is_empty()).then_some(Reference { sector, position })
+    }
+
+    /// Returns the objects of the array, their members aren't parsed until they are accessed
+    pub fn items(&self) -> Result<&[LazyObject<'a>], GrannyError> {
+        if let Some(items) = self.items.get() {
+            return Ok(items)
+        }
+
+        let (sector, offset) = self.data;
+        if self.len == 0 {
+            return Ok(self.items.get_or_init(Vec::new))
+        }
+
+        let item_size = match self.layout {
+            Layout::Contiguous => self.context.struct_size(self.type_ref)?,
+            Layout::References => self.context.pointer_size()
+        };
+
+        // The items have to fit into the sector before anything is allocated for them, empty
+        // structs are counted as one byte so their count is limited as well
+        let sector_length = self.context.sector(sector)?.data.len();
+        let end = item_size.max(1).checked_mul(self.len).and_then(|size| size.checked_add(offset));
+        if end.is_none_or(|end| end as usize > sector_length) {
+            return Err(GrannyError::TruncatedData)
+        }
+
+        let mut items = Vec::with_capacity(self.len as usize);
+        match self.layout {
+            Layout::Contiguous => {
+                for i in 0..self.len {
+                    items.push(LazyObject::new(&self.context, (sector, offset + i * item_size), self.type_ref));
+                }
+            }
+            Layout::References => {
+                for i in 0..self.len {
+                    let position = offset + i * item_size;
+                    items.push(match self.context.pointer(sector, position)? {
+                        Some(data) => LazyObject::new(&self.context, data, self.type_ref),
+                        None => LazyObject::null(&self.context)
+                    });
+                }
+            }
+        }
+
+        Ok(self.items.get_or_init(|| items))
+    }
+
+    pub fn get(&self, index: usize) -> Result<Option<&LazyObject<'a>>, GrannyError> {
+        Ok(self.items()?.get(index))
+    }
+
+    /// Parses all objects into elements
+    pub fn to_items(&self) -> Result<Vec<Vec<Element<'a>>>, GrannyError> {
+        self.items()?.iter().map(LazyObject::to_elements).collect()
+    }
+}
+
+impl Debug for LazyArray<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyArray")
+            .field("len", &self.len)
+            .field("items", &self.items.get())
+            .finish()
+    }
+}
\ No newline at end of file
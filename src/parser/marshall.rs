@@ -0,0 +1,32 @@
+use nom::IResult;
+use nom::number::complete::u32;
+use nom::number::Endianness;
+use nom::sequence::tuple;
+
+/// An entry of the marshalling table, marks `count` objects at `offset` which contain 8 or 16 bit
+/// members and need to be byte swapped member by member
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Marshall {
+    pub count: u32,
+    pub offset: u32,
+    pub type_sector: u32,
+    pub type_offset: u32,
+}
+
+pub fn parse_marshall(endianness: Endianness) -> impl FnMut(&[u8]) -> IResult<&[u8], Marshall> {
+    move |input| {
+        let count = u32(endianness);
+        let offset = u32(endianness);
+        let type_sector = u32(endianness);
+        let type_offset = u32(endianness);
+
+        let (input, (count, offset, type_sector, type_offset)) = tuple((count, offset, type_sector, type_offset))(input)?;
+
+        Ok((input, Marshall {
+            count,
+            offset,
+            type_sector,
+            type_offset,
+        }))
+    }
+}
\ No newline at end of file
@@ -4,10 +4,21 @@ mod reference;
 mod sector;
 mod element;
 mod pointer;
+mod marshall;
+mod lazy;
+mod graph;
+mod view;
+mod type_tree;
 
 pub use header::*;
 pub use file_info::*;
 pub use reference::*;
 pub use sector::*;
 pub use element::*;
-pub use pointer::*;
\ No newline at end of file
+pub use pointer::*;
+pub use marshall::*;
+pub use lazy::{LazyArray, LazyElement, LazyElementType, LazyObject};
+pub use graph::*;
+pub use view::{ArrayValue, ArrayView, Primitive};
+pub use type_tree::*;
+pub(crate) use lazy::Context;
\ No newline at end of file
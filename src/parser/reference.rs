@@ -3,7 +3,7 @@ use nom::number::complete::u32;
 use nom::number::Endianness;
 use nom::sequence::tuple;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Reference {
     pub sector: u32,
     pub position: u32,
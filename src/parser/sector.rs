@@ -63,6 +63,7 @@ pub fn parse_sector_info(endianness: Endianness) -> impl FnMut(&[u8]) -> IResult
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use nom::number::Endianness;
     use crate::parser::{parse_sector_info, SectorInfo};
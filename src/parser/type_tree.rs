@@ -0,0 +1,306 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::error::GrannyError;
+use crate::granny_path::split_path;
+use super::{Context, Element, ElementType, Reference, TypeInfo};
+
+/// The type of a member, the ids are the ones stored in the file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemberType {
+    Inline,
+    Reference,
+    ReferenceToArray,
+    ArrayOfReferences,
+    VariantReference,
+    ReferenceToVariantArray,
+    String,
+    Transform,
+    Real32,
+    Int8,
+    UInt8,
+    BinormalInt8,
+    NormalUInt8,
+    Int16,
+    UInt16,
+    BinormalInt16,
+    NormalUInt16,
+    Int32,
+    UInt32,
+    Real16,
+    EmptyReference,
+    /// A type id which isn't known, such members can't be parsed
+    Unknown(u32)
+}
+
+impl MemberType {
+    pub fn from_id(type_id: u32) -> MemberType {
+        match type_id {
+            1 => MemberType::Inline,
+            2 => MemberType::Reference,
+            3 => MemberType::ReferenceToArray,
+            4 => MemberType::ArrayOfReferences,
+            5 => MemberType::VariantReference,
+            7 => MemberType::ReferenceToVariantArray,
+            8 => MemberType::String,
+            9 => MemberType::Transform,
+            10 => MemberType::Real32,
+            11 => MemberType::Int8,
+            12 => MemberType::UInt8,
+            13 => MemberType::BinormalInt8,
+            14 => MemberType::NormalUInt8,
+            15 => MemberType::Int16,
+            16 => MemberType::UInt16,
+            17 => MemberType::BinormalInt16,
+            18 => MemberType::NormalUInt16,
+            19 => MemberType::Int32,
+            20 => MemberType::UInt32,
+            21 => MemberType::Real16,
+            22 => MemberType::EmptyReference,
+            type_id => MemberType::Unknown(type_id)
+        }
+    }
+
+    /// Returns the type id which is stored in the file
+    pub fn id(&self) -> u32 {
+        match self {
+            MemberType::Inline => 1,
+            MemberType::Reference => 2,
+            MemberType::ReferenceToArray => 3,
+            MemberType::ArrayOfReferences => 4,
+            MemberType::VariantReference => 5,
+            MemberType::ReferenceToVariantArray => 7,
+            MemberType::String => 8,
+            MemberType::Transform => 9,
+            MemberType::Real32 => 10,
+            MemberType::Int8 => 11,
+            MemberType::UInt8 => 12,
+            MemberType::BinormalInt8 => 13,
+            MemberType::NormalUInt8 => 14,
+            MemberType::Int16 => 15,
+            MemberType::UInt16 => 16,
+            MemberType::BinormalInt16 => 17,
+            MemberType::NormalUInt16 => 18,
+            MemberType::Int32 => 19,
+            MemberType::UInt32 => 20,
+            MemberType::Real16 => 21,
+            MemberType::EmptyReference => 22,
+            MemberType::Unknown(type_id) => *type_id
+        }
+    }
+}
+
+/// A member of a struct type
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDefinition<'a> {
+    /// The location of the definition, elements keep it in [Element::type_ref]
+    pub location: Reference,
+    pub name: Cow<'a, str>,
+    pub member_type: MemberType,
+    /// The number of values for arrays inside the object, 0 for a single value
+    pub array_size: i32,
+    /// The type of inline, referenced and array members
+    ///
+    /// Variants store their type next to the data, so they don't have a child type.
+    pub children: Option<Reference>,
+    /// Exporter specific values which Granny doesn't interpret
+    pub extra: [u32; 3]
+}
+
+/// A struct type, Granny doesn't store names for types
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDefinition<'a> {
+    pub members: Vec<MemberDefinition<'a>>
+}
+
+/// All types which are reachable from a root type, types can reference themselves
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeTree<'a> {
+    pub types: BTreeMap<Reference, TypeDefinition<'a>>,
+    /// The type of the root object, `FileInfo::type_ref` for a whole file
+    pub root: Reference
+}
+
+impl<'a> TypeDefinition<'a> {
+    pub(crate) fn load(context: &Context<'a>, type_ref: Reference) -> Result<TypeDefinition<'a>, GrannyError> {
+        let members = context.type_infos((type_ref.sector, type_ref.position))?
+            .iter()
+            .enumerate()
+            .map(|(index, type_info)| {
+                let location = Reference { sector: type_ref.sector, position: type_ref.position + index as u32 * context.definition_size() };
+                MemberDefinition::new(context, type_info, location)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(TypeDefinition { members })
+    }
+
+    pub fn member(&self, name: &str) -> Option<&MemberDefinition<'a>> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+impl<'a> MemberDefinition<'a> {
+    fn new(context: &Context<'a>, type_info: &TypeInfo, location: Reference) -> Result<MemberDefinition<'a>, GrannyError> {
+        Ok(MemberDefinition {
+            location,
+            name: context.name(type_info)?,
+            member_type: MemberType::from_id(type_info.type_id),
+            array_size: type_info.array_size,
+            children: type_info.children_offset.map(|pointer| Reference { sector: pointer.dst_sector, position: pointer.dst_offset }),
+            extra: type_info.extra
+        })
+    }
+
+    pub fn into_owned(self) -> MemberDefinition<'static> {
+        MemberDefinition {
+            name: Cow::Owned(self.name.into_owned()),
+            ..self
+        }
+    }
+}
+
+impl<'a> TypeTree<'a> {
+    /// Loads the root type and all types its members reference
+    pub(crate) fn load(context: &Context<'a>, root: Reference) -> Result<TypeTree<'a>, GrannyError> {
+        let mut tree = TypeTree { types: BTreeMap::new(), root };
+        tree.insert(context, root)?;
+
+        Ok(tree)
+    }
+
+    /// Loads the root type and the types of the variants in `elements`, which were parsed with it
+    pub(crate) fn load_with_variants(context: &Context<'a>, root: Reference, elements: &[Element]) -> Result<TypeTree<'a>, GrannyError> {
+        let mut tree = TypeTree::load(context, root)?;
+        tree.insert_variants(context, elements)?;
+
+        Ok(tree)
+    }
+
+    /// Loads a type and all types its members reference unless they are already in the tree
+    fn insert(&mut self, context: &Context<'a>, type_ref: Reference) -> Result<(), GrannyError> {
+        let mut pending = vec![type_ref];
+
+        while let Some(type_ref) = pending.pop() {
+            if self.types.contains_key(&type_ref) {
+                continue
+            }
+
+            let definition = TypeDefinition::load(context, type_ref)?;
+            pending.extend(definition.members.iter().filter_map(|member| member.children));
+            self.types.insert(type_ref, definition);
+        }
+
+        Ok(())
+    }
+
+    /// Inserts the types of variants, a struct starts with the definition of its first member
+    fn insert_variants(&mut self, context: &Context<'a>, elements: &[Element]) -> Result<(), GrannyError> {
+        for element in elements {
+            match &element.element {
+                ElementType::Variant { elements, .. } => {
+                    if let Some(type_ref) = elements.first().and_then(|element| element.type_ref) {
+                        self.insert(context, type_ref)?;
+                    }
+                    self.insert_variants(context, elements)?;
+                }
+                ElementType::ReferenceToVariantArray(items) => {
+                    if let Some(type_ref) = items.first().and_then(|item| item.first()).and_then(|element| element.type_ref) {
+                        self.insert(context, type_ref)?;
+                    }
+                    for item in items {
+                        self.insert_variants(context, item)?;
+                    }
+                }
+                ElementType::Reference(elements) | ElementType::Inline(elements) => self.insert_variants(context, elements)?,
+                ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) => {
+                    for item in items {
+                        self.insert_variants(context, item)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, type_ref: &Reference) -> Option<&TypeDefinition<'a>> {
+        self.types.get(type_ref)
+    }
+
+    /// Returns the member definition at `location`, e.g. the [Element::type_ref] of an element
+    pub fn member(&self, location: &Reference) -> Option<&MemberDefinition<'a>> {
+        // The definition belongs to the last type which starts before it
+        let (_, definition) = self.types.range(..=location).next_back()?;
+        definition.members.iter().find(|member| member.location == *location)
+    }
+
+    pub fn root_type(&self) -> &TypeDefinition<'a> {
+        &self.types[&self.root]
+    }
+
+    /// Looks up the definition of the member at a path of the element tree (e.g. `Meshes[0].PrimaryVertexData`)
+    ///
+    /// All items of an array share their type, so indices are only checked for their syntax. Paths
+    /// can't continue after a variant because its type is stored in the data.
+    pub fn resolve(&self, path: &str) -> Option<&MemberDefinition<'a>> {
+        let mut definition = self.root_type();
+        let mut path = path;
+
+        loop {
+            let (name, _, rest) = split_path(path)?;
+            let member = definition.member(name)?;
+
+            match rest {
+                Some(rest) => {
+                    definition = self.get(&member.children?)?;
+                    path = rest;
+                }
+                None => return Some(member)
+            }
+        }
+    }
+
+    pub fn into_owned(self) -> TypeTree<'static> {
+        TypeTree {
+            types: self.types.into_iter().map(|(type_ref, definition)| (type_ref, TypeDefinition {
+                members: definition.members.into_iter().map(MemberDefinition::into_owned).collect()
+            })).collect(),
+            root: self.root
+        }
+    }
+}
+
+impl Display for MemberDefinition<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?}", self.name, self.member_type)?;
+        if self.array_size > 0 {
+            write!(f, "[{}]", self.array_size)?;
+        }
+        if let Some(children) = self.children {
+            write!(f, " -> {}:{}", children.sector, children.position)?;
+        }
+        if self.extra != [0; 3] {
+            write!(f, " {:?}", self.extra)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Prints one type per block with a member per line, sorted by the location of the types
+impl Display for TypeTree<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (type_ref, definition) in &self.types {
+            let root = if *type_ref == self.root { " (root)" } else { "" };
+            writeln!(f, "type {}:{}{}", type_ref.sector, type_ref.position, root)?;
+
+            for member in &definition.members {
+                writeln!(f, "    {}", member)?;
+            }
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,182 @@
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use crate::error::GrannyError;
+use super::lazy::Layout;
+use super::{LazyArray, TypeInfo};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A number which is stored in the sector data as is
+///
+/// Integers can be viewed with either signedness, half floats are viewed as their `u16` bits.
+pub trait Primitive: sealed::Sealed + Copy {
+    /// The member types which are stored like this primitive
+    const TYPE_IDS: &'static [u32];
+}
+
+macro_rules! primitive {
+    ($($ty:ty => $type_ids:expr);* $(;)?) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl Primitive for $ty {
+                const TYPE_IDS: &'static [u32] = $type_ids;
+            }
+        )*
+    };
+}
+
+primitive! {
+    u8 => &[11, 12, 13, 14];
+    i8 => &[11, 12, 13, 14];
+    u16 => &[15, 16, 17, 18, 21];
+    i16 => &[15, 16, 17, 18];
+    u32 => &[19, 20];
+    i32 => &[19, 20];
+    f32 => &[10];
+}
+
+/// A value of an array item, either a single primitive or a fixed number of them (e.g. `[f32; 3]`)
+pub trait ArrayValue: sealed::Sealed + Copy {
+    type Primitive: Primitive;
+    /// The number of primitives in the value
+    const COUNT: usize;
+}
+
+impl<P: Primitive> ArrayValue for P {
+    type Primitive = P;
+    const COUNT: usize = 1;
+}
+
+impl<P: Primitive, const N: usize> sealed::Sealed for [P; N] {}
+
+impl<P: Primitive, const N: usize> ArrayValue for [P; N] {
+    type Primitive = P;
+    const COUNT: usize = N;
+}
+
+/// A typed view of a value in each item of an array, which reads directly from the sector data
+///
+/// Files which don't use the native byte order are read from their sector data in native byte
+/// order ([native_sector_data](crate::sector::native_sector_data)), which is swapped once per sector.
+pub struct ArrayView<'s, T: ArrayValue> {
+    data: &'s [u8],
+    len: usize,
+    stride: usize,
+    value_type: PhantomData<T>
+}
+
+impl<'s, T: ArrayValue> ArrayView<'s, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the distance in bytes between two values
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None
+        }
+
+        let bytes = &self.data[index * self.stride..index * self.stride + size_of::<T>()];
+        // SAFETY: `bytes` has the size of `T` which is made of primitives that are valid for any bit pattern
+        Some(unsafe { bytes.as_ptr().cast::<T>().read_unaligned() })
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = T> + '_ {
+        (0..self.len).map(|index| self.get(index).unwrap())
+    }
+
+    /// Returns the values as a slice of the sector data without copying them
+    ///
+    /// This is `None` if the values aren't stored one after another or the data isn't aligned for `T`.
+    pub fn as_slice(&self) -> Option<&'s [T]> {
+        let packed = self.stride == size_of::<T>() || self.len <= 1;
+        if !packed || self.data.as_ptr().align_offset(align_of::<T>()) != 0 {
+            return None
+        }
+
+        // SAFETY: the data is aligned, holds `len` values of `T` and any bit pattern is a valid `T`
+        Some(unsafe { std::slice::from_raw_parts(self.data.as_ptr().cast::<T>(), self.len) })
+    }
+
+    /// Copies the values
+    pub fn to_vec(&self) -> Vec<T> {
+        match self.as_slice() {
+            Some(values) => values.to_vec(),
+            None => self.iter().collect()
+        }
+    }
+}
+
+/// Checks that a member holds exactly one value of `T`
+fn matches_value<T: ArrayValue>(type_info: &TypeInfo) -> bool {
+    T::Primitive::TYPE_IDS.contains(&type_info.type_id) && type_info.array_size.max(1) as usize == T::COUNT
+}
+
+impl LazyArray<'_> {
+    /// Views the items of an array whose type is a single value of `T` (e.g. the `Int32` of `Indices`)
+    pub fn view<T: ArrayValue>(&self) -> Result<ArrayView<'_, T>, GrannyError> {
+        let type_infos = if self.is_empty() { Vec::new() } else { self.context.type_infos(self.type_ref)? };
+
+        match type_infos.as_slice() {
+            [] if self.is_empty() => self.create_view(0),
+            [type_info] if matches_value::<T>(type_info) => self.create_view(0),
+            [type_info, ..] => Err(GrannyError::WrongFieldType(self.context.name(type_info)?.into_owned())),
+            [] => Err(GrannyError::WrongFieldType(String::new()))
+        }
+    }
+
+    /// Views a member of each item (e.g. the `[f32; 3]` of `Position` in `Vertices`)
+    pub fn view_member<T: ArrayValue>(&self, name: &str) -> Result<ArrayView<'_, T>, GrannyError> {
+        if self.is_empty() {
+            return self.create_view(0)
+        }
+
+        let mut offset = 0;
+        for type_info in self.context.type_infos(self.type_ref)? {
+            if self.context.name(&type_info)? == name {
+                if !matches_value::<T>(&type_info) {
+                    return Err(GrannyError::WrongFieldType(name.to_string()))
+                }
+
+                return self.create_view(offset)
+            }
+
+            offset += self.context.member_size(&type_info, self.type_ref)? * type_info.array_size.max(1) as u32;
+        }
+
+        Err(GrannyError::MissingField(name.to_string()))
+    }
+
+    /// Creates a view of the values at `offset` in each item
+    fn create_view<T: ArrayValue>(&self, offset: u32) -> Result<ArrayView<'_, T>, GrannyError> {
+        if self.layout != Layout::Contiguous {
+            return Err(GrannyError::WrongFieldType(String::new()))
+        }
+
+        if self.is_empty() {
+            return Ok(ArrayView { data: &[], len: 0, stride: size_of::<T>(), value_type: PhantomData })
+        }
+
+        let (sector, start) = self.data;
+        let stride = self.context.struct_size(self.type_ref)? as usize;
+        let start = start as usize + offset as usize;
+        let end = stride.checked_mul(self.len() - 1)
+            .and_then(|size| size.checked_add(start + size_of::<T>()))
+            .ok_or(GrannyError::TruncatedData)?;
+
+        let data = self.context.native_sector(sector)?.get(start..end).ok_or(GrannyError::TruncatedData)?;
+
+        Ok(ArrayView { data, len: self.len(), stride, value_type: PhantomData })
+    }
+}
\ No newline at end of file
@@ -1,40 +1,203 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use nom::number::Endianness;
 use crate::decompression::decompress_sector;
-use crate::parser::{parse_pointer, Pointer, SectorInfo};
+use crate::error::GrannyError;
+use crate::parser::{FileInfo, Header, Marshall, parse_file_info, parse_header, parse_marshall, parse_pointer, parse_sector_info, parse_type_info, Pointer, SectorInfo};
 
 #[derive(Debug)]
-pub struct Sector {
+pub struct Sector<'a> {
     pub info: SectorInfo,
-    pub data: Vec<u8>,
-    pub pointer_table: HashMap<u32, Pointer>
+    /// The sector data, only decompressed sectors are copied
+    pub data: Cow<'a, [u8]>,
+    pub pointer_table: HashMap<u32, Pointer>,
+    /// Objects which need to be byte swapped member by member
+    pub marshall_table: Vec<Marshall>
 }
 
-pub fn load_sector(input: &[u8], endianness: Endianness, info: SectorInfo) -> Sector {
-    let data = decompress_sector(input, &info);
+pub fn load_sector(input: &[u8], endianness: Endianness, info: SectorInfo) -> Result<Sector<'_>, GrannyError> {
+    let data = decompress_sector(input, &info)?;
     let mut pointer_table = HashMap::new();
 
-    let mut fixup_input = &input[info.fixup_offset as usize..];
+    let mut fixup_input = input.get(info.fixup_offset as usize..).ok_or(GrannyError::TruncatedData)?;
     for _ in 0..info.fixup_size {
-        let res = parse_pointer(endianness)(fixup_input);
-        if let Ok((next, pointer)) = res {
-            pointer_table.insert(pointer.src_offset, pointer);
+        let (next, pointer) = parse_pointer(endianness)(fixup_input)
+            .map_err(|_| GrannyError::TruncatedData)?;
 
-            fixup_input = next
-        } else {
-            panic!("Failed to load fixup table for sector");
-        }
+        pointer_table.insert(pointer.src_offset, pointer);
+
+        fixup_input = next
+    }
+
+    let mut marshall_table = Vec::new();
+
+    let mut marshall_input = input.get(info.marshall_offset as usize..).ok_or(GrannyError::TruncatedData)?;
+    for _ in 0..info.marshall_size {
+        let (next, marshall) = parse_marshall(endianness)(marshall_input)
+            .map_err(|_| GrannyError::TruncatedData)?;
+
+        marshall_table.push(marshall);
+
+        marshall_input = next
     }
 
-    Sector {
+    Ok(Sector {
         info,
         data,
         pointer_table,
+        marshall_table
+    })
+}
+
+/// Parses the header and file info, returns them with the endianness and the following input
+fn parse_file_header(bytes: &[u8]) -> Result<(&[u8], Endianness, Header, FileInfo), GrannyError> {
+    let (data, header) = parse_header(bytes).map_err(|_| {
+        if bytes.len() < 32 {
+            GrannyError::TruncatedHeader
+        } else {
+            GrannyError::BadMagic
+        }
+    })?;
+
+    let endianness = if header.big_endian {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
+
+    let (data, file_info) = parse_file_info(endianness)(data).map_err(|_| GrannyError::TruncatedHeader)?;
+
+    Ok((data, endianness, header, file_info))
+}
+
+/// Parses the header and file info without loading any sector
+pub fn load_file_info(bytes: &[u8]) -> Result<(Header, FileInfo), GrannyError> {
+    let (_, _, header, file_info) = parse_file_header(bytes)?;
+
+    Ok((header, file_info))
+}
+
+/// Parses the header and file info and loads all sectors
+pub fn load_sectors(bytes: &[u8]) -> Result<(Header, FileInfo, Vec<Sector<'_>>), GrannyError> {
+    let (mut data, endianness, header, file_info) = parse_file_header(bytes)?;
+
+    let mut sectors = Vec::new();
+
+    for _ in 0..file_info.sector_count {
+        let (next_input, sector) = parse_sector_info(endianness)(data).map_err(|_| GrannyError::TruncatedHeader)?;
+
+        sectors.push(load_sector(bytes, endianness, sector)?);
+
+        data = next_input;
     }
+
+    Ok((header, file_info, sectors))
 }
 
-impl Sector {
+impl Sector<'_> {
     pub fn resolve_pointer(&self, offset: usize) -> Option<Pointer> {
         self.pointer_table.get(&(offset as u32)).copied()
     }
+}
+
+/// Copies `size` bytes at `offset` in reversed order and returns the offset after them
+fn swap(original: &[u8], data: &mut [u8], offset: usize, size: usize) -> Result<usize, GrannyError> {
+    let bytes = original.get(offset..offset + size).ok_or(GrannyError::TruncatedData)?;
+
+    for (target, source) in data[offset..offset + size].iter_mut().zip(bytes.iter().rev()) {
+        *target = *source;
+    }
+
+    Ok(offset + size)
+}
+
+/// Swaps the members of a single object and returns the offset after it
+///
+/// `ancestors` are the types the object is inlined into, a type which inlines itself is reported as cyclic.
+#[allow(clippy::too_many_arguments)]
+fn swap_object(sectors: &[Sector], original: &[u8], data: &mut [u8], mut offset: usize, (type_sector, type_offset): (u32, u32), endianness: Endianness, is_64bits: bool, ancestors: &mut Vec<(u32, u32)>) -> Result<usize, GrannyError> {
+    if ancestors.contains(&(type_sector, type_offset)) {
+        return Err(GrannyError::CyclicReference { sector: type_sector, offset: type_offset })
+    }
+    ancestors.push((type_sector, type_offset));
+
+    let types = sectors.get(type_sector as usize).ok_or(GrannyError::SectorOutOfRange(type_sector))?;
+    let pointer_size = if is_64bits { 8 } else { 4 };
+    let definition_size = 4 + pointer_size * 3 + 4 + 12;
+
+    for i in 0.. {
+        let position = type_offset as usize + i * definition_size;
+        let input = types.data.get(position..).ok_or(GrannyError::BadPointer { sector: type_sector, offset: position as u32 })?;
+        let (_, type_info) = parse_type_info(endianness, types, is_64bits, position as u32)(input)
+            .map_err(|_| GrannyError::TruncatedData)?;
+
+        if type_info.type_id == 0 {
+            break
+        }
+
+        for _ in 0..type_info.array_size.max(1) {
+            // Sizes of the values of the member which are swapped separately
+            let values: &[usize] = match type_info.type_id {
+                1 => {
+                    let children = type_info.children_offset.ok_or(GrannyError::BadPointer { sector: type_sector, offset: position as u32 })?;
+                    offset = swap_object(sectors, original, data, offset, (children.dst_sector, children.dst_offset), endianness, is_64bits, ancestors)?;
+                    continue
+                }
+                2 | 8 | 22 => &[pointer_size],
+                3 | 4 => &[4, pointer_size],
+                5 => &[pointer_size, pointer_size],
+                7 => &[pointer_size, 4, pointer_size],
+                9 => &[4; 17],
+                10 | 19 | 20 => &[4],
+                11..=14 => &[1],
+                15..=18 | 21 => &[2],
+                type_id => return Err(GrannyError::UnknownMemberType(type_id))
+            };
+
+            for size in values {
+                offset = swap(original, data, offset, *size)?;
+            }
+        }
+    }
+
+    ancestors.pop();
+    Ok(offset)
+}
+
+/// Returns the data of a sector in the byte order of the current platform
+///
+/// Like Granny does it, data is swapped as 32 bit values up to `oodle_stop_0`, as 16 bit values up
+/// to `oodle_stop_1` and the rest (strings) isn't swapped. Then the objects of the marshalling
+/// table are swapped member by member.
+pub fn native_sector_data(sectors: &[Sector], sector: u32, endianness: Endianness, is_64bits: bool) -> Result<Vec<u8>, GrannyError> {
+    let source = sectors.get(sector as usize).ok_or(GrannyError::SectorOutOfRange(sector))?;
+    let mut data = source.data.to_vec();
+
+    if (endianness == Endianness::Big) == cfg!(target_endian = "big") {
+        return Ok(data)
+    }
+
+    let length = data.len();
+    let first_16_bit = (source.info.oodle_stop_0 as usize).min(length);
+    let first_8_bit = (source.info.oodle_stop_1 as usize).clamp(first_16_bit, length);
+
+    for word in data[..first_16_bit].chunks_exact_mut(4) {
+        word.reverse();
+    }
+    for word in data[first_16_bit..first_8_bit].chunks_exact_mut(2) {
+        word.reverse();
+    }
+
+    for marshall in &source.marshall_table {
+        let mut offset = marshall.offset as usize;
+        for _ in 0..marshall.count {
+            offset = swap_object(sectors, &source.data, &mut data, offset, (marshall.type_sector, marshall.type_offset), endianness, is_64bits, &mut Vec::new())
+                .map_err(|error| match error {
+                    GrannyError::TruncatedData => GrannyError::BadPointer { sector, offset: marshall.offset },
+                    error => error
+                })?;
+        }
+    }
+
+    Ok(data)
 }
\ No newline at end of file
@@ -0,0 +1,385 @@
+use std::collections::{HashMap, VecDeque};
+use crate::error::GrannyError;
+use crate::granny_file::FileFormat;
+use crate::parser::Pointer;
+use crate::sector::{load_sectors, Sector};
+use crate::writer::{align, assemble_file, pointer_size, put_u16, put_u32, SectorData};
+
+/// An object which is referenced by a pointer, identified by its location in the source file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Block {
+    /// `count` objects of the type at `type_ref`
+    Objects { sector: u32, offset: u32, type_ref: (u32, u32), count: u32 },
+    /// `count` pointers to single objects of the type at `type_ref`
+    References { sector: u32, offset: u32, type_ref: (u32, u32), count: u32 },
+    /// Type definitions up to the end marker
+    Types { sector: u32, offset: u32 },
+    /// A zero terminated string
+    String { sector: u32, offset: u32 }
+}
+
+impl Block {
+    fn location(&self) -> (u32, u32) {
+        match *self {
+            Block::Objects { sector, offset, .. } | Block::References { sector, offset, .. } |
+            Block::Types { sector, offset } | Block::String { sector, offset } => (sector, offset)
+        }
+    }
+}
+
+/// A type definition of the source file
+struct MemberDefinition {
+    type_id: u32,
+    name: Option<Pointer>,
+    children: Option<Pointer>,
+    array_size: i32,
+    extra: [u32; 3]
+}
+
+impl MemberDefinition {
+    fn children_ref(&self, sector: u32, offset: u32) -> Result<(u32, u32), GrannyError> {
+        self.children.map(|pointer| (pointer.dst_sector, pointer.dst_offset)).ok_or(GrannyError::BadPointer { sector, offset })
+    }
+}
+
+/// Copies all objects which are reachable from the root and the root type into the target format
+///
+/// Every member is byte swapped according to its type and pointers are widened or narrowed, so the
+/// layout of objects changes and they are written again one after another in their sector.
+struct Converter<'a> {
+    sectors: &'a [Sector<'a>],
+    source: FileFormat,
+    target: FileFormat,
+    output: Vec<SectorData>,
+    offsets: HashMap<Block, u32>,
+    queue: VecDeque<Block>,
+    /// Pointers in the output (sector, offset) which are fixed up once their block is written
+    pointers: Vec<(usize, u32, Block)>,
+    /// Objects in the output (sector, offset, count) which need marshalling with their type block
+    marshalls: Vec<(usize, u32, u32, Block)>,
+    marshalling: HashMap<(u32, u32), bool>
+}
+
+impl<'a> Converter<'a> {
+    fn data(&self, sector: u32, offset: u32, size: usize) -> Result<&'a [u8], GrannyError> {
+        self.sectors.get(sector as usize)
+            .ok_or(GrannyError::SectorOutOfRange(sector))?
+            .data.get(offset as usize..offset as usize + size)
+            .ok_or(GrannyError::BadPointer { sector, offset })
+    }
+
+    fn read_u16(&self, sector: u32, offset: u32) -> Result<u16, GrannyError> {
+        let bytes = self.data(sector, offset, 2)?.try_into().unwrap();
+        Ok(if self.source.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn read_u32(&self, sector: u32, offset: u32) -> Result<u32, GrannyError> {
+        let bytes = self.data(sector, offset, 4)?.try_into().unwrap();
+        Ok(if self.source.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn pointer(&self, sector: u32, offset: u32) -> Result<Option<Pointer>, GrannyError> {
+        Ok(self.sectors.get(sector as usize).ok_or(GrannyError::SectorOutOfRange(sector))?.resolve_pointer(offset as usize))
+    }
+
+    fn read_type(&self, (sector, offset): (u32, u32)) -> Result<Vec<MemberDefinition>, GrannyError> {
+        let pointer_size = pointer_size(self.source) as u32;
+        let definition_size = 4 + pointer_size * 3 + 4 + 12;
+
+        let mut members = Vec::new();
+        for i in 0.. {
+            let position = offset + i * definition_size;
+            let type_id = self.read_u32(sector, position)?;
+            if type_id == 0 {
+                break
+            }
+
+            let extra = position + 4 + pointer_size * 2 + 4;
+            members.push(MemberDefinition {
+                type_id,
+                name: self.pointer(sector, position + 4)?,
+                children: self.pointer(sector, position + 4 + pointer_size)?,
+                array_size: self.read_u32(sector, position + 4 + pointer_size * 2)? as i32,
+                extra: [self.read_u32(sector, extra)?, self.read_u32(sector, extra + 4)?, self.read_u32(sector, extra + 8)?]
+            });
+        }
+
+        Ok(members)
+    }
+
+    /// Returns if objects of the type contain 8 or 16 bit values, `ancestors` are the types it is inlined into
+    fn needs_marshalling(&mut self, type_ref: (u32, u32), ancestors: &mut Vec<(u32, u32)>) -> Result<bool, GrannyError> {
+        if let Some(result) = self.marshalling.get(&type_ref) {
+            return Ok(*result)
+        }
+        if ancestors.contains(&type_ref) {
+            return Err(GrannyError::CyclicReference { sector: type_ref.0, offset: type_ref.1 })
+        }
+        ancestors.push(type_ref);
+
+        let mut result = false;
+        for member in self.read_type(type_ref)? {
+            result |= match member.type_id {
+                1 => self.needs_marshalling(member.children_ref(type_ref.0, type_ref.1)?, ancestors)?,
+                11..=18 | 21 => true,
+                _ => false
+            };
+        }
+
+        ancestors.pop();
+        self.marshalling.insert(type_ref, result);
+        Ok(result)
+    }
+
+    /// Writes a null pointer in the target format which is fixed up to point to `block`
+    fn write_pointer(&mut self, sector: usize, block: Option<Block>) {
+        let data = &mut self.output[sector].data;
+        let position = data.len() as u32;
+        data.resize(data.len() + pointer_size(self.target), 0);
+
+        if let Some(block) = block {
+            self.pointers.push((sector, position, block));
+            self.queue.push_back(block);
+        }
+    }
+
+    fn convert(&mut self) -> Result<(), GrannyError> {
+        while let Some(block) = self.queue.pop_front() {
+            if self.offsets.contains_key(&block) {
+                continue
+            }
+
+            let (sector, offset) = block.location();
+            let output = sector as usize;
+            if output >= self.output.len() {
+                return Err(GrannyError::SectorOutOfRange(sector))
+            }
+
+            if let Block::String { .. } = block {
+                let bytes = self.sectors[output].data.get(offset as usize..).ok_or(GrannyError::BadPointer { sector, offset })?;
+                let length = bytes.iter().position(|&b| b == 0).ok_or(GrannyError::InvalidString)?;
+
+                let string = self.output[output].push_string(&bytes[..length]);
+                self.offsets.insert(block, string);
+                continue
+            }
+
+            let data = &mut self.output[output].data;
+            data.resize(align(data.len(), pointer_size(self.target)), 0);
+            self.offsets.insert(block, data.len() as u32);
+
+            match block {
+                Block::Objects { type_ref, count, .. } => {
+                    if self.needs_marshalling(type_ref, &mut Vec::new())? {
+                        self.marshalls.push((output, self.output[output].data.len() as u32, count, Block::Types { sector: type_ref.0, offset: type_ref.1 }));
+                        self.queue.push_back(Block::Types { sector: type_ref.0, offset: type_ref.1 });
+                    }
+
+                    let mut position = offset;
+                    for _ in 0..count {
+                        position += self.convert_object(sector, position, type_ref, &mut Vec::new())?;
+                    }
+                }
+                Block::References { type_ref, count, .. } => {
+                    for i in 0..count {
+                        let target = self.pointer(sector, offset + i * pointer_size(self.source) as u32)?;
+                        let block = target.map(|pointer| Block::Objects { sector: pointer.dst_sector, offset: pointer.dst_offset, type_ref, count: 1 });
+                        self.write_pointer(output, block);
+                    }
+                }
+                Block::Types { .. } => self.convert_types(sector, offset)?,
+                Block::String { .. } => unreachable!()
+            }
+        }
+
+        Ok(())
+    }
+
+    fn convert_types(&mut self, sector: u32, offset: u32) -> Result<(), GrannyError> {
+        let output = sector as usize;
+
+        for member in self.read_type((sector, offset))? {
+            put_u32(&mut self.output[output].data, member.type_id, self.target);
+
+            let name = member.name.map(|pointer| Block::String { sector: pointer.dst_sector, offset: pointer.dst_offset });
+            self.write_pointer(output, name);
+
+            let children = member.children.map(|pointer| Block::Types { sector: pointer.dst_sector, offset: pointer.dst_offset });
+            self.write_pointer(output, children);
+
+            put_u32(&mut self.output[output].data, member.array_size as u32, self.target);
+            for value in member.extra {
+                put_u32(&mut self.output[output].data, value, self.target);
+            }
+            self.write_pointer(output, None);
+        }
+
+        // End marker
+        let data = &mut self.output[output].data;
+        data.resize(data.len() + 4 + pointer_size(self.target) * 3 + 4 + 12, 0);
+
+        Ok(())
+    }
+
+    /// Converts a single object and returns its size in the source file
+    ///
+    /// `ancestors` are the objects (data sector, data offset, type sector, type offset) it is inlined into.
+    fn convert_object(&mut self, sector: u32, offset: u32, type_ref: (u32, u32), ancestors: &mut Vec<[u32; 4]>) -> Result<u32, GrannyError> {
+        let object = [sector, offset, type_ref.0, type_ref.1];
+        if ancestors.contains(&object) {
+            return Err(GrannyError::CyclicReference { sector, offset })
+        }
+        ancestors.push(object);
+
+        let mut position = offset;
+        for member in self.read_type(type_ref)? {
+            for _ in 0..member.array_size.max(1) {
+                position += self.convert_member(sector, position, &member, type_ref, ancestors)?;
+            }
+        }
+
+        ancestors.pop();
+        Ok(position - offset)
+    }
+
+    /// Converts a member and returns its size in the source file
+    fn convert_member(&mut self, sector: u32, offset: u32, member: &MemberDefinition, type_ref: (u32, u32), ancestors: &mut Vec<[u32; 4]>) -> Result<u32, GrannyError> {
+        let output = sector as usize;
+        let source_pointer = pointer_size(self.source) as u32;
+        let objects = |pointer: Pointer, type_ref: (u32, u32), count: u32| Block::Objects { sector: pointer.dst_sector, offset: pointer.dst_offset, type_ref, count };
+
+        let size = match member.type_id {
+            1 => self.convert_object(sector, offset, member.children_ref(type_ref.0, type_ref.1)?, ancestors)?,
+            2 => {
+                let block = match self.pointer(sector, offset)? {
+                    Some(pointer) => Some(objects(pointer, member.children_ref(type_ref.0, type_ref.1)?, 1)),
+                    None => None
+                };
+                self.write_pointer(output, block);
+                source_pointer
+            }
+            3 | 4 => {
+                let count = self.read_u32(sector, offset)?;
+                put_u32(&mut self.output[output].data, count, self.target);
+
+                let block = match self.pointer(sector, offset + 4)? {
+                    Some(pointer) if count > 0 => {
+                        let children = member.children_ref(type_ref.0, type_ref.1)?;
+                        Some(if member.type_id == 3 {
+                            objects(pointer, children, count)
+                        } else {
+                            Block::References { sector: pointer.dst_sector, offset: pointer.dst_offset, type_ref: children, count }
+                        })
+                    }
+                    _ => None
+                };
+                self.write_pointer(output, block);
+                4 + source_pointer
+            }
+            5 | 7 => {
+                let count_size = if member.type_id == 7 { 4 } else { 0 };
+                let count = if member.type_id == 7 { self.read_u32(sector, offset + source_pointer)? } else { 1 };
+
+                let type_pointer = self.pointer(sector, offset)?;
+                let data_pointer = self.pointer(sector, offset + source_pointer + count_size)?;
+
+                let (types, data) = match (type_pointer, data_pointer) {
+                    (Some(type_pointer), Some(data_pointer)) if count > 0 => (
+                        Some(Block::Types { sector: type_pointer.dst_sector, offset: type_pointer.dst_offset }),
+                        Some(objects(data_pointer, (type_pointer.dst_sector, type_pointer.dst_offset), count))
+                    ),
+                    _ => (None, None)
+                };
+
+                self.write_pointer(output, types);
+                if member.type_id == 7 {
+                    put_u32(&mut self.output[output].data, count, self.target);
+                }
+                self.write_pointer(output, data);
+                source_pointer * 2 + count_size
+            }
+            8 => {
+                let block = self.pointer(sector, offset)?.map(|pointer| Block::String { sector: pointer.dst_sector, offset: pointer.dst_offset });
+                self.write_pointer(output, block);
+                source_pointer
+            }
+            9 => {
+                for i in 0..17 {
+                    let value = self.read_u32(sector, offset + i * 4)?;
+                    put_u32(&mut self.output[output].data, value, self.target);
+                }
+                68
+            }
+            10 | 19 | 20 => {
+                let value = self.read_u32(sector, offset)?;
+                put_u32(&mut self.output[output].data, value, self.target);
+                4
+            }
+            11..=14 => {
+                let value = self.data(sector, offset, 1)?[0];
+                self.output[output].data.push(value);
+                1
+            }
+            15..=18 | 21 => {
+                let value = self.read_u16(sector, offset)?;
+                put_u16(&mut self.output[output].data, value, self.target);
+                2
+            }
+            22 => {
+                self.write_pointer(output, None);
+                source_pointer
+            }
+            type_id => return Err(GrannyError::UnknownMemberType(type_id))
+        };
+
+        Ok(size)
+    }
+}
+
+/// Converts a file of any supported format into an uncompressed format 7 file with the given byte order and pointer size
+///
+/// Unlike loading and saving the file, this keeps the original type tree and objects which are
+/// referenced from multiple places.
+pub fn convert(bytes: &[u8], format: FileFormat) -> Result<Vec<u8>, GrannyError> {
+    let (header, file_info, sectors) = load_sectors(bytes)?;
+
+    let root = Block::Objects {
+        sector: file_info.root_ref.sector,
+        offset: file_info.root_ref.position,
+        type_ref: (file_info.type_ref.sector, file_info.type_ref.position),
+        count: 1
+    };
+    let root_type = Block::Types { sector: file_info.type_ref.sector, offset: file_info.type_ref.position };
+
+    let mut converter = Converter {
+        sectors: &sectors,
+        source: FileFormat { big_endian: header.big_endian, bits_64: header.bits_64 },
+        target: format,
+        output: sectors.iter().map(|sector| SectorData::new(sector.info.alignment)).collect(),
+        offsets: HashMap::new(),
+        queue: VecDeque::from([root, root_type]),
+        pointers: Vec::new(),
+        marshalls: Vec::new(),
+        marshalling: HashMap::new()
+    };
+    converter.convert()?;
+
+    for (sector, position, block) in &converter.pointers {
+        let (target_sector, _) = block.location();
+        let fixup = [*position, target_sector, converter.offsets[block]];
+
+        match block {
+            Block::String { .. } => converter.output[*sector].string_fixups.push(fixup),
+            _ => converter.output[*sector].fixups.push(fixup)
+        }
+    }
+    for (sector, position, count, block) in &converter.marshalls {
+        let (type_sector, _) = block.location();
+        converter.output[*sector].marshalls.push([*count, *position, type_sector, converter.offsets[block]]);
+    }
+
+    let root_ref = [root.location().0, converter.offsets[&root]];
+    let type_ref = [root_type.location().0, converter.offsets[&root_type]];
+
+    Ok(assemble_file(&converter.output, format, type_ref, root_ref, file_info.tag))
+}
\ No newline at end of file
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+use crate::error::GrannyError;
+use crate::granny_file::FileFormat;
+use crate::parser::{Element, ElementType, Reference, TypeTree};
+use crate::writer::{align, pointer_size, put_f32, put_u16, put_u32, SectorData};
+
+/// Sector which holds the type tree
+pub const TYPE_SECTOR: usize = 6;
+/// Sector which holds the items of references to variant arrays (e.g. vertices)
+const VERTEX_SECTOR: usize = 1;
+
+/// The type of the children of a member
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Children {
+    /// Members which were inferred from the elements
+    Members(Vec<MemberType>),
+    /// A struct of the type tree the elements were loaded with
+    Source(Reference)
+}
+
+/// The type of a member
+///
+/// Elements which were loaded from a file are written with the types of its type tree as long as
+/// they still match them. The types of all other elements are inferred, which leaves the children
+/// of null references and empty arrays unknown unless the element was loaded from a file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemberType {
+    name: String,
+    type_id: u32,
+    array_size: i32,
+    extra: [u32; 3],
+    children: Option<Children>
+}
+
+/// Returns the values of an element, members with an array size are stored as `ElementType::Array`
+fn values<'e, 'a>(element: &'e ElementType<'a>, array_size: i32) -> Option<Vec<&'e ElementType<'a>>> {
+    match element {
+        ElementType::Array(values) if array_size > 0 && values.len() == array_size as usize => Some(values.iter().collect()),
+        ElementType::Array(_) => None,
+        value if array_size == 0 => Some(vec![value]),
+        _ => None
+    }
+}
+
+/// Looks up the member types of elements in the type tree they were loaded with or infers them
+#[derive(Copy, Clone)]
+struct TypeSource<'t> {
+    tree: Option<&'t TypeTree<'t>>
+}
+
+impl TypeSource<'_> {
+    /// Returns the members of a struct of the source type tree
+    fn source_members(&self, type_ref: Reference) -> Vec<MemberType> {
+        let Some(definition) = self.tree.and_then(|tree| tree.get(&type_ref)) else {
+            return Vec::new()
+        };
+
+        definition.members.iter().map(|member| MemberType {
+            name: member.name.to_string(),
+            type_id: member.member_type.id(),
+            array_size: member.array_size,
+            extra: member.extra,
+            children: member.children.map(Children::Source)
+        }).collect()
+    }
+
+    fn members(&self, children: &Option<Children>) -> Vec<MemberType> {
+        match children {
+            Some(Children::Members(members)) => members.clone(),
+            Some(Children::Source(type_ref)) => self.source_members(*type_ref),
+            None => Vec::new()
+        }
+    }
+
+    /// Checks if the elements and everything they reference still match a struct of the source type tree
+    fn matches_source(&self, elements: &[Element], type_ref: Reference) -> bool {
+        let Some(definition) = self.tree.and_then(|tree| tree.get(&type_ref)) else {
+            return false
+        };
+
+        definition.members.len() == elements.len() && elements.iter().zip(&definition.members).all(|(element, member)| {
+            let children = |elements: &[Element]| elements.is_empty() || member.children.is_some_and(|children| self.matches_source(elements, children));
+
+            element.name == member.name && values(&element.element, member.array_size).is_some_and(|values| values.into_iter().all(|value| {
+                type_id(value) == Some(member.member_type.id()) && match value {
+                    ElementType::Inline(elements) => member.children.is_some_and(|children| self.matches_source(elements, children)),
+                    ElementType::Reference(elements) => children(elements),
+                    ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) => items.iter().all(|item| children(item)),
+                    // Variants store their type next to the data, see `struct_type`
+                    _ => true
+                }
+            }))
+        })
+    }
+
+    /// Returns the type of a struct, which is the one of the source type tree if the elements still match it
+    fn struct_type(&self, elements: &[Element]) -> Result<Vec<MemberType>, GrannyError> {
+        // A struct starts with the definition of its first member
+        if let Some(type_ref) = elements.first().and_then(|element| element.type_ref) {
+            if self.matches_source(elements, type_ref) {
+                return Ok(self.source_members(type_ref))
+            }
+        }
+
+        elements.iter().map(|element| self.infer_member(element)).collect()
+    }
+
+    fn infer_member(&self, element: &Element) -> Result<MemberType, GrannyError> {
+        let wrong_type = || GrannyError::WrongFieldType(element.name.to_string());
+        let (values, array_size) = match &element.element {
+            ElementType::Array(values) => (values.iter().collect(), values.len() as i32),
+            value => (vec![value], 0)
+        };
+
+        // The definition the element was loaded with knows the types which can't be inferred
+        let source = element.type_ref
+            .and_then(|type_ref| self.tree?.member(&type_ref))
+            .filter(|member| member.name == element.name && member.array_size == array_size);
+        let type_id = match values.first() {
+            Some(value) => type_id(value).ok_or_else(wrong_type)?,
+            None => source.map(|member| member.member_type.id()).ok_or_else(wrong_type)?
+        };
+        let source = source.filter(|member| member.member_type.id() == type_id);
+
+        // Items of arrays can reference different data, all of it is described by the same type
+        let mut children = None;
+        for value in values {
+            merge(&mut children, self.children_type(value)?);
+        }
+
+        Ok(MemberType {
+            name: element.name.to_string(),
+            type_id,
+            array_size,
+            extra: source.map_or([0; 3], |member| member.extra),
+            children: children.or_else(|| source.and_then(|member| member.children).map(Children::Source))
+        })
+    }
+
+    fn children_type(&self, value: &ElementType) -> Result<Option<Children>, GrannyError> {
+        Ok(match value {
+            ElementType::Inline(elements) => Some(Children::Members(self.struct_type(elements)?)),
+            ElementType::Reference(elements) if !elements.is_empty() => Some(Children::Members(self.struct_type(elements)?)),
+            ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) => self.items_type(items)?,
+            _ => None
+        })
+    }
+
+    /// Returns the type which describes all items, `None` if they are all empty
+    fn items_type(&self, items: &[Vec<Element>]) -> Result<Option<Children>, GrannyError> {
+        let mut children = None;
+        for item in items.iter().filter(|item| !item.is_empty()) {
+            merge(&mut children, Some(Children::Members(self.struct_type(item)?)));
+        }
+
+        Ok(children)
+    }
+
+    /// Returns if the type contains 8 or 16 bit values which need a marshalling entry to be byte swapped
+    fn needs_marshalling(&self, members: &[MemberType]) -> bool {
+        members.iter().any(|member| match member.type_id {
+            1 => self.needs_marshalling(&self.members(&member.children)),
+            11..=18 | 21 => true,
+            _ => false
+        })
+    }
+}
+
+/// Fills the unknown member types of `a` with the ones from `b`
+fn merge(a: &mut Option<Children>, b: Option<Children>) {
+    match (a, b) {
+        (a @ None, b) => *a = b,
+        (Some(Children::Members(a)), Some(Children::Members(b))) if a.len() == b.len() => {
+            for (a, b) in a.iter_mut().zip(b) {
+                merge(&mut a.children, b.children);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the id of the member type which stores the value, `None` for arrays
+fn type_id(value: &ElementType) -> Option<u32> {
+    Some(match value {
+        ElementType::Inline(_) => 1,
+        ElementType::Reference(_) => 2,
+        ElementType::ReferenceToArray(_) => 3,
+        ElementType::ArrayOfReferences(_) => 4,
+        ElementType::ReferenceToVariantArray(_) => 7,
+        ElementType::Variant { .. } => 5,
+        ElementType::String(_) => 8,
+        ElementType::Transform(_) => 9,
+        ElementType::F32(_) => 10,
+        ElementType::I8(_) => 11,
+        ElementType::U8(_) => 12,
+        ElementType::BinormalI8(_) => 13,
+        ElementType::NormalU8(_) => 14,
+        ElementType::I16(_) => 15,
+        ElementType::U16(_) => 16,
+        ElementType::BinormalI16(_) => 17,
+        ElementType::NormalU16(_) => 18,
+        ElementType::I32(_) => 19,
+        ElementType::U32(_) => 20,
+        ElementType::F16(_) => 21,
+        ElementType::EmptyReference => 22,
+        ElementType::Array(_) => return None
+    })
+}
+
+/// Converts a float 32 into the closest half precision value
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7FFFFF;
+
+    if exponent == 0xFF {
+        // Infinity keeps an empty mantissa while NaN keeps at least one bit
+        let nan = if mantissa != 0 { 0x200 | (mantissa >> 13) as u16 } else { 0 };
+        return sign | 0x7C00 | nan
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1F {
+        return sign | 0x7C00
+    }
+
+    let (mantissa, shift) = if exponent <= 0 {
+        if exponent < -10 {
+            return sign
+        }
+        // Subnormal values include the implicit leading bit in the mantissa
+        (mantissa | 0x800000, (14 - exponent) as u32)
+    } else {
+        (mantissa, 13)
+    };
+
+    // Round to nearest, ties to even, a carry into the exponent is handled by the addition
+    let half = 1 << (shift - 1);
+    let remainder = mantissa & ((1 << shift) - 1);
+    let mut result = (mantissa >> shift) + if exponent > 0 { (exponent as u32) << 10 } else { 0 };
+    if remainder > half || (remainder == half && result & 1 != 0) {
+        result += 1;
+    }
+
+    sign | result as u16
+}
+
+/// A pointer which is written after the block it's contained in
+enum Target<'a> {
+    Block { sector: usize, items: Vec<&'a [Element<'a>]>, members: Vec<MemberType> },
+    References { sector: usize, items: &'a [Vec<Element<'a>>], members: Vec<MemberType> },
+    Type(Vec<MemberType>),
+    String(&'a str)
+}
+
+/// Lays out the data and type tree in sectors
+pub struct Layout<'t> {
+    format: FileFormat,
+    source: TypeSource<'t>,
+    pub sectors: Vec<SectorData>,
+    types: HashMap<Vec<MemberType>, u32>,
+    names: HashMap<String, u32>
+}
+
+impl<'t> Layout<'t> {
+    /// Creates an empty layout, `source` is the type tree the elements were loaded with
+    pub fn new(format: FileFormat, sector_count: usize, source: Option<&'t TypeTree<'t>>) -> Layout<'t> {
+        Layout {
+            format,
+            source: TypeSource { tree: source },
+            sectors: (0..sector_count).map(|i| SectorData::new(if i == VERTEX_SECTOR || i == 3 { 32 } else { 4 })).collect(),
+            types: HashMap::new(),
+            names: HashMap::new()
+        }
+    }
+
+    /// Writes the root elements and returns the offset of their type
+    pub fn write_root(&mut self, elements: &[Element]) -> Result<u32, GrannyError> {
+        let members = self.source.struct_type(elements)?;
+        self.write_block(0, vec![elements], &members)?;
+
+        Ok(self.write_type(&members))
+    }
+
+    fn reserve(&mut self, sector: usize, size: usize) -> u32 {
+        let data = &mut self.sectors[sector].data;
+        data.resize(align(data.len(), pointer_size(self.format)), 0);
+
+        let offset = data.len();
+        data.resize(offset + size, 0);
+        offset as u32
+    }
+
+    /// Writes a type definition for each member followed by the end marker, equal types are written once
+    fn write_type(&mut self, members: &[MemberType]) -> u32 {
+        if let Some(offset) = self.types.get(members) {
+            return *offset
+        }
+
+        let pointer_size = pointer_size(self.format);
+        let definition_size = 4 + pointer_size * 3 + 4 + 12;
+        let offset = self.reserve(TYPE_SECTOR, definition_size * (members.len() + 1));
+        self.types.insert(members.to_vec(), offset);
+
+        for (i, member) in members.iter().enumerate() {
+            let position = offset as usize + i * definition_size;
+
+            let mut definition = Vec::with_capacity(definition_size);
+            put_u32(&mut definition, member.type_id, self.format);
+            definition.resize(4 + pointer_size * 2, 0);
+            put_u32(&mut definition, member.array_size as u32, self.format);
+            for value in member.extra {
+                put_u32(&mut definition, value, self.format);
+            }
+            definition.resize(definition_size, 0);
+            self.sectors[TYPE_SECTOR].data[position..position + definition_size].copy_from_slice(&definition);
+
+            if !member.name.is_empty() {
+                let name = self.write_name(&member.name);
+                self.sectors[TYPE_SECTOR].string_fixups.push([(position + 4) as u32, TYPE_SECTOR as u32, name]);
+            }
+
+            if matches!(member.type_id, 1..=4) {
+                let children = self.write_type(&self.source.members(&member.children));
+                self.sectors[TYPE_SECTOR].fixups.push([(position + 4 + pointer_size) as u32, TYPE_SECTOR as u32, children]);
+            }
+        }
+
+        offset
+    }
+
+    fn write_name(&mut self, name: &str) -> u32 {
+        if let Some(offset) = self.names.get(name) {
+            return *offset
+        }
+
+        let offset = self.sectors[TYPE_SECTOR].push_string(name.as_bytes());
+        self.names.insert(name.to_string(), offset);
+        offset
+    }
+
+    /// Writes the items one after another and returns their offset
+    fn write_block(&mut self, sector: usize, items: Vec<&[Element]>, members: &[MemberType]) -> Result<u32, GrannyError> {
+        let offset = self.reserve(sector, 0);
+        let mut targets = Vec::new();
+
+        for item in &items {
+            self.write_members(sector, item, members, &mut targets)?;
+        }
+
+        if self.source.needs_marshalling(members) {
+            let type_offset = self.write_type(members);
+            self.sectors[sector].marshalls.push([items.len() as u32, offset, TYPE_SECTOR as u32, type_offset]);
+        }
+
+        for (position, target) in targets {
+            if let Target::String(value) = target {
+                let string = self.sectors[sector].push_string(value.as_bytes());
+                self.sectors[sector].string_fixups.push([position, sector as u32, string]);
+                continue
+            }
+
+            let (target_sector, target_offset) = match target {
+                Target::Block { sector, items, members } => (sector, self.write_block(sector, items, &members)?),
+                Target::References { sector, items, members } => {
+                    let offset = self.reserve(sector, items.len() * pointer_size(self.format));
+
+                    for (i, item) in items.iter().enumerate().filter(|(_, item)| !item.is_empty()) {
+                        let item_offset = self.write_block(sector, vec![item.as_slice()], &members)?;
+                        let position = offset as usize + i * pointer_size(self.format);
+                        self.sectors[sector].fixups.push([position as u32, sector as u32, item_offset]);
+                    }
+
+                    (sector, offset)
+                }
+                Target::Type(members) => (TYPE_SECTOR, self.write_type(&members)),
+                Target::String(_) => unreachable!()
+            };
+
+            self.sectors[sector].fixups.push([position, target_sector as u32, target_offset]);
+        }
+
+        Ok(offset)
+    }
+
+    fn write_members<'a>(&mut self, sector: usize, elements: &'a [Element<'a>], members: &[MemberType], targets: &mut Vec<(u32, Target<'a>)>) -> Result<(), GrannyError> {
+        // Items of arrays have to share their type
+        if elements.len() != members.len() {
+            return Err(GrannyError::WrongFieldType(elements.first().map(|e| e.name.to_string()).unwrap_or_default()))
+        }
+
+        for (element, member) in elements.iter().zip(members) {
+            let values = values(&element.element, member.array_size)
+                .filter(|values| element.name == member.name && values.iter().all(|value| type_id(value) == Some(member.type_id)))
+                .ok_or_else(|| GrannyError::WrongFieldType(element.name.to_string()))?;
+
+            for value in values {
+                self.write_value(sector, value, member, targets)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_value<'a>(&mut self, sector: usize, value: &'a ElementType, member: &MemberType, targets: &mut Vec<(u32, Target<'a>)>) -> Result<(), GrannyError> {
+        let format = self.format;
+        let pointer_size = pointer_size(format);
+        let source = self.source;
+        let children = || source.members(&member.children);
+        let position = self.sectors[sector].data.len() as u32;
+        let data = &mut self.sectors[sector].data;
+
+        match value {
+            ElementType::Inline(elements) => {
+                let members = children();
+                self.write_members(sector, elements, &members, targets)?;
+            }
+            ElementType::Reference(elements) => {
+                data.resize(data.len() + pointer_size, 0);
+                if !elements.is_empty() {
+                    targets.push((position, Target::Block { sector, items: vec![elements], members: children() }));
+                }
+            }
+            ElementType::ReferenceToArray(items) => {
+                put_u32(data, items.len() as u32, format);
+                data.resize(data.len() + pointer_size, 0);
+                if !items.is_empty() {
+                    targets.push((position + 4, Target::Block { sector, items: items.iter().map(Vec::as_slice).collect(), members: children() }));
+                }
+            }
+            ElementType::ReferenceToVariantArray(items) => {
+                data.resize(data.len() + pointer_size, 0);
+                put_u32(data, items.len() as u32, format);
+                data.resize(data.len() + pointer_size, 0);
+                if !items.is_empty() {
+                    let members = source.members(&source.items_type(items)?);
+                    targets.push((position, Target::Type(members.clone())));
+                    targets.push((position + pointer_size as u32 + 4, Target::Block {
+                        sector: VERTEX_SECTOR,
+                        items: items.iter().map(Vec::as_slice).collect(),
+                        members
+                    }));
+                }
+            }
+            ElementType::ArrayOfReferences(items) => {
+                put_u32(data, items.len() as u32, format);
+                data.resize(data.len() + pointer_size, 0);
+                if !items.is_empty() {
+                    targets.push((position + 4, Target::References { sector, items, members: children() }));
+                }
+            }
+            ElementType::Variant { type_name, elements } => {
+                data.resize(data.len() + pointer_size * 2, 0);
+                if type_name.is_some() {
+                    let members = source.struct_type(elements)?;
+                    targets.push((position, Target::Type(members.clone())));
+                    targets.push((position + pointer_size as u32, Target::Block { sector, items: vec![elements], members }));
+                }
+            }
+            ElementType::String(value) => {
+                data.resize(data.len() + pointer_size, 0);
+                if !value.is_empty() {
+                    targets.push((position, Target::String(value)));
+                }
+            }
+            ElementType::Transform(transform) => {
+                put_u32(data, transform.flags, format);
+                for value in transform.translation.iter().chain(&transform.rotation).chain(transform.scale_shear.iter().flatten()) {
+                    put_f32(data, *value, format);
+                }
+            }
+            ElementType::F32(value) => put_f32(data, *value, format),
+            ElementType::F16(value) => put_u16(data, f32_to_f16(*value), format),
+            ElementType::I8(value) | ElementType::BinormalI8(value) => data.push(*value as u8),
+            ElementType::U8(value) | ElementType::NormalU8(value) => data.push(*value),
+            ElementType::I16(value) | ElementType::BinormalI16(value) => put_u16(data, *value as u16, format),
+            ElementType::U16(value) | ElementType::NormalU16(value) => put_u16(data, *value, format),
+            ElementType::I32(value) => put_u32(data, *value as u32, format),
+            ElementType::U32(value) => put_u32(data, *value, format),
+            ElementType::EmptyReference => data.resize(data.len() + pointer_size, 0),
+            ElementType::Array(_) => return Err(GrannyError::WrongFieldType(member.name.clone()))
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::writer::element::f32_to_f16;
+
+    #[test]
+    fn test_f32_to_f16() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(1.0), 0x3C00);
+        assert_eq!(f32_to_f16(-2.0), 0xC000);
+        assert_eq!(f32_to_f16(0.33325195), 0x3555);
+        assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16(65520.0), 0x7C00);
+        assert_eq!(f32_to_f16(5.9604645e-8), 0x0001);
+        assert_eq!(f32_to_f16(2.9802322e-8), 0x0000);
+        assert_eq!(f32_to_f16(6.097555e-5), 0x03FF);
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3C00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7C00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7E00, 0x7E00);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,165 @@
+mod convert;
+mod element;
+
+use crate::crc::crc32;
+use crate::error::GrannyError;
+use crate::granny_file::FileFormat;
+use crate::parser::{Element, TypeTree};
+use element::{Layout, TYPE_SECTOR};
+
+pub use convert::convert;
+
+const SECTOR_COUNT: usize = 8;
+const HEADER_SIZE: usize = 32;
+const FILE_INFO_SIZE: usize = 72;
+const SECTOR_INFO_SIZE: usize = 44;
+const FORMAT_VERSION: u32 = 7;
+
+/// The data of a sector with its fixups (source offset, target sector, target offset) and
+/// marshalling entries (count, offset, type sector, type offset)
+///
+/// Strings are stored after the data, so they aren't byte swapped with it. Their fixups point to
+/// an offset in the strings of the target sector.
+pub struct SectorData {
+    pub alignment: u32,
+    pub data: Vec<u8>,
+    pub strings: Vec<u8>,
+    pub fixups: Vec<[u32; 3]>,
+    pub string_fixups: Vec<[u32; 3]>,
+    pub marshalls: Vec<[u32; 4]>
+}
+
+impl SectorData {
+    pub fn new(alignment: u32) -> SectorData {
+        SectorData {
+            alignment,
+            data: Vec::new(),
+            strings: Vec::new(),
+            fixups: Vec::new(),
+            string_fixups: Vec::new(),
+            marshalls: Vec::new()
+        }
+    }
+
+    /// Appends a zero terminated string and returns its offset in the strings
+    pub fn push_string(&mut self, value: &[u8]) -> u32 {
+        let offset = self.strings.len() as u32;
+        self.strings.extend(value);
+        self.strings.push(0);
+        offset
+    }
+
+    fn strings_offset(&self) -> usize {
+        align(self.data.len(), 4)
+    }
+}
+
+pub fn align(value: usize, alignment: usize) -> usize {
+    value.next_multiple_of(alignment)
+}
+
+pub fn pointer_size(format: FileFormat) -> usize {
+    if format.bits_64 { 8 } else { 4 }
+}
+
+pub fn put_u16(data: &mut Vec<u8>, value: u16, format: FileFormat) {
+    data.extend(if format.big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+}
+
+pub fn put_u32(data: &mut Vec<u8>, value: u32, format: FileFormat) {
+    data.extend(if format.big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+}
+
+pub fn put_f32(data: &mut Vec<u8>, value: f32, format: FileFormat) {
+    put_u32(data, value.to_bits(), format);
+}
+
+/// Returns the magic value of a format 7 file, see `parse_header`
+fn magic(format: FileFormat) -> [u8; 16] {
+    match (format.big_endian, format.bits_64) {
+        (false, false) => [0x29, 0xDE, 0x6C, 0xC0, 0xBA, 0xA4, 0x53, 0x2B, 0x25, 0xF5, 0xB7, 0xA5, 0xF6, 0x66, 0xE2, 0xEE],
+        (false, true) => [0xE5, 0x9B, 0x49, 0x5E, 0x6F, 0x63, 0x1F, 0x14, 0x1E, 0x13, 0xEB, 0xA9, 0x90, 0xBE, 0xED, 0xC4],
+        (true, false) => [0xB5, 0x95, 0x11, 0x0E, 0x4B, 0xB5, 0xA5, 0x6A, 0x50, 0x28, 0x28, 0xEB, 0x04, 0xB3, 0x78, 0x25],
+        (true, true) => [0xE3, 0xD4, 0x95, 0x31, 0x62, 0x4F, 0xDC, 0x20, 0x3A, 0xD0, 0x36, 0xCC, 0x89, 0xFF, 0x82, 0xB1]
+    }
+}
+
+/// Writes an uncompressed format 7 file with the standard sector layout
+///
+/// The root elements are written to sector 0, vertices to sector 1 and the type tree to sector 6.
+/// `types` is the type tree the elements were loaded with, their member types are kept if the elements still match them.
+pub fn write_file(root_elements: &[Element], types: Option<&TypeTree>, format: FileFormat, tag: u32) -> Result<Vec<u8>, GrannyError> {
+    let mut layout = Layout::new(format, SECTOR_COUNT, types);
+    let type_offset = layout.write_root(root_elements)?;
+
+    Ok(assemble_file(&layout.sectors, format, [TYPE_SECTOR as u32, type_offset], [0, 0], tag))
+}
+
+/// Writes the header, file info and sector table followed by the data and the fixup and marshalling tables of all sectors
+pub fn assemble_file(sectors: &[SectorData], format: FileFormat, type_ref: [u32; 2], root_ref: [u32; 2], tag: u32) -> Vec<u8> {
+    let data_start = HEADER_SIZE + FILE_INFO_SIZE + sectors.len() * SECTOR_INFO_SIZE;
+    let sector_sizes: Vec<usize> = sectors.iter().map(|sector| align(sector.strings_offset() + sector.strings.len(), 4)).collect();
+
+    let mut sector_infos = Vec::new();
+    let mut tables = Vec::new();
+    let mut data_offset = data_start;
+    let mut table_offset = data_start + sector_sizes.iter().sum::<usize>();
+
+    for (sector, size) in sectors.iter().zip(&sector_sizes) {
+        let string_fixups = sector.string_fixups.iter().map(|&[offset, target_sector, target_offset]| {
+            [offset, target_sector, target_offset + sectors[target_sector as usize].strings_offset() as u32]
+        });
+
+        let fixup_offset = table_offset;
+        for value in sector.fixups.iter().copied().chain(string_fixups).flatten() {
+            put_u32(&mut tables, value, format);
+        }
+        let fixup_count = sector.fixups.len() + sector.string_fixups.len();
+        let marshall_offset = fixup_offset + fixup_count * 12;
+        for value in sector.marshalls.iter().flatten() {
+            put_u32(&mut tables, *value, format);
+        }
+        table_offset = marshall_offset + sector.marshalls.len() * 16;
+
+        // All data is swapped as 32 bit values up to the strings, which aren't swapped at all
+        let strings_offset = sector.strings_offset();
+        let info = [
+            0, data_offset, *size, *size, sector.alignment as usize, strings_offset, strings_offset,
+            fixup_offset, fixup_count, marshall_offset, sector.marshalls.len()
+        ];
+        for value in info {
+            put_u32(&mut sector_infos, value as u32, format);
+        }
+
+        data_offset += size;
+    }
+
+    let total_size = table_offset;
+
+    let mut body = sector_infos;
+    for (sector, size) in sectors.iter().zip(&sector_sizes) {
+        let start = body.len();
+        body.extend(&sector.data);
+        body.resize(start + sector.strings_offset(), 0);
+        body.extend(&sector.strings);
+        body.resize(start + size, 0);
+    }
+    body.extend(tables);
+
+    let mut file = Vec::with_capacity(total_size);
+    file.extend(magic(format));
+    put_u32(&mut file, data_start as u32, format);
+    file.resize(HEADER_SIZE, 0);
+
+    let file_info = [
+        FORMAT_VERSION, total_size as u32, crc32(&body), FILE_INFO_SIZE as u32, sectors.len() as u32,
+        type_ref[0], type_ref[1], root_ref[0], root_ref[1], tag
+    ];
+    for value in file_info {
+        put_u32(&mut file, value, format);
+    }
+    file.resize(HEADER_SIZE + FILE_INFO_SIZE, 0);
+    file.extend(body);
+
+    file
+}
\ No newline at end of file
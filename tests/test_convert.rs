@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use nom::number::Endianness;
+use opengr2::{convert, FileFormat, GrannyError, GrannyFile, Verification};
+use opengr2::parser::{Marshall, Pointer, SectorInfo};
+use opengr2::sector::{load_sectors, native_sector_data, Sector};
+
+const FORMATS: [FileFormat; 4] = [FileFormat::LITTLE_ENDIAN_32, FileFormat::LITTLE_ENDIAN_64, FileFormat::BIG_ENDIAN_32, FileFormat::BIG_ENDIAN_64];
+
+#[test]
+fn test_convert() {
+    let files = [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_textured_external.gr2").as_slice(),
+        include_bytes!("../assets/prova.gr2").as_slice(),
+        include_bytes!("../assets/test1.gr2").as_slice()
+    ];
+
+    for bytes in files {
+        let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+
+        for format in FORMATS {
+            let converted_bytes = convert(bytes, format).unwrap();
+            let converted = GrannyFile::load_from_bytes_with(&converted_bytes, Verification::Strict).unwrap();
+            assert_eq!(converted.format, format);
+            assert_eq!(converted.tag, granny_file.tag);
+            assert_eq!(converted.root_elements, granny_file.root_elements);
+        }
+    }
+}
+
+#[test]
+fn test_convert_suzanne_variants() {
+    let le = include_bytes!("../assets/suzanne_le.gr2");
+    let le64 = include_bytes!("../assets/suzanne_le64.gr2");
+    let be = include_bytes!("../assets/suzanne_be.gr2");
+    let be64 = include_bytes!("../assets/suzanne_be64.gr2");
+
+    // The fixtures were exported from the same scene, converting any of them gives the same bytes
+    for format in FORMATS {
+        let converted = convert(le, format).unwrap();
+        assert_eq!(convert(be, format).unwrap(), converted);
+        assert_eq!(convert(le64, format).unwrap(), converted);
+        assert_eq!(convert(be64, format).unwrap(), converted);
+    }
+
+    // Shared objects are kept, so the converted file is about as large as the exported one
+    assert!(convert(le, FileFormat::BIG_ENDIAN_32).unwrap().len() < be.len() * 11 / 10);
+    assert!(convert(le, FileFormat::LITTLE_ENDIAN_64).unwrap().len() < le64.len() * 11 / 10);
+}
+
+#[test]
+fn test_convert_round_trip() {
+    let le = include_bytes!("../assets/suzanne_le.gr2");
+    let converted = convert(le, FileFormat::LITTLE_ENDIAN_32).unwrap();
+
+    for format in FORMATS {
+        assert_eq!(convert(&convert(&converted, format).unwrap(), FileFormat::LITTLE_ENDIAN_32).unwrap(), converted);
+    }
+}
+
+#[test]
+fn test_convert_native_sector_data() {
+    let le = convert(include_bytes!("../assets/suzanne_le.gr2"), FileFormat::LITTLE_ENDIAN_32).unwrap();
+    let be = convert(&le, FileFormat::BIG_ENDIAN_32).unwrap();
+
+    let (_, _, le_sectors) = load_sectors(&le).unwrap();
+    let (_, _, be_sectors) = load_sectors(&be).unwrap();
+
+    // Strings are kept out of the swapped region, so both files have the same native data
+    for sector in 0..le_sectors.len() as u32 {
+        assert_eq!(
+            native_sector_data(&le_sectors, sector, Endianness::Little, false).unwrap(),
+            native_sector_data(&be_sectors, sector, Endianness::Big, false).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_native_sector_data_cyclic_type() {
+    // Swap the sector whatever the byte order of the platform is
+    let (endianness, type_id) = if cfg!(target_endian = "big") {
+        (Endianness::Little, 1u32.to_le_bytes())
+    } else {
+        (Endianness::Big, 1u32.to_be_bytes())
+    };
+
+    // An inline member whose children are the type itself, followed by the end of the type
+    let mut data = vec![0; 96];
+    data[0..4].copy_from_slice(&type_id);
+
+    let sector = Sector {
+        info: SectorInfo {
+            compression_type: 0,
+            data_offset: 0,
+            compressed_length: 96,
+            decompressed_length: 96,
+            alignment: 4,
+            oodle_stop_0: 96,
+            oodle_stop_1: 96,
+            fixup_offset: 0,
+            fixup_size: 0,
+            marshall_offset: 0,
+            marshall_size: 0
+        },
+        data: Cow::Owned(data),
+        pointer_table: HashMap::from([(8, Pointer { src_offset: 8, dst_sector: 0, dst_offset: 0 })]),
+        marshall_table: vec![Marshall { count: 1, offset: 64, type_sector: 0, type_offset: 0 }]
+    };
+
+    assert!(matches!(
+        native_sector_data(&[sector], 0, endianness, false),
+        Err(GrannyError::CyclicReference { sector: 0, offset: 0 })
+    ));
+}
+
+#[test]
+fn test_convert_cyclic_inline_type() {
+    let mut bytes = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+
+    // The root type starts sector 6, its first member becomes an inline member of the root type itself
+    let type_sector = 35564;
+    bytes[type_sector..type_sector + 4].copy_from_slice(&1u32.to_le_bytes());
+    let fixup: Vec<u8> = [8u32, 6, 448].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let position = bytes.windows(12).position(|window| window == fixup).unwrap();
+    bytes[position + 8..position + 12].copy_from_slice(&0u32.to_le_bytes());
+
+    assert!(matches!(GrannyFile::load_from_bytes(&bytes), Err(GrannyError::CyclicReference { sector: 0, offset: 0 })));
+    assert!(matches!(convert(&bytes, FileFormat::BIG_ENDIAN_32), Err(GrannyError::CyclicReference { .. })));
+}
\ No newline at end of file
@@ -0,0 +1,103 @@
+#![cfg(feature = "gltf")]
+
+use std::fs;
+use opengr2::{GrannyError, GrannyFile};
+use opengr2::export::gltf::{to_glb, to_gltf};
+use opengr2::parser::{Element, ElementType};
+
+/// Compares the output with the stored snapshot, set `UPDATE_SNAPSHOTS` to write new snapshots
+fn assert_snapshot(name: &str, output: &str) {
+    let path = format!("{}/tests/snapshots/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, output).unwrap();
+    }
+
+    assert_eq!(output, fs::read_to_string(&path).unwrap(), "snapshot {} differs", name);
+}
+
+#[test]
+fn test_suzanne_gltf() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let (json, buffer) = to_gltf(&granny_file, "suzanne_le.bin").unwrap();
+    assert_snapshot("suzanne_le.gltf", &json);
+    assert_eq!(buffer.len() % 4, 0);
+
+    // The export doesn't depend on anything but the file
+    assert_eq!(to_gltf(&granny_file, "suzanne_le.bin").unwrap(), (json, buffer));
+}
+
+#[test]
+fn test_suzanne_endianness() {
+    let le = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_le.gr2")).unwrap();
+    let be = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_be.gr2")).unwrap();
+
+    let (_, le_buffer) = to_gltf(&le, "suzanne.bin").unwrap();
+    let (_, be_buffer) = to_gltf(&be, "suzanne.bin").unwrap();
+    assert_eq!(le_buffer, be_buffer);
+}
+
+#[test]
+fn test_textured_external_gltf() {
+    let data = include_bytes!("../assets/suzanne_textured_external.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let (json, _) = to_gltf(&granny_file, "suzanne_textured_external.bin").unwrap();
+    assert_snapshot("suzanne_textured_external.gltf", &json);
+    assert!(json.contains(r#""images":[{"uri":"texture.png"}]"#));
+}
+
+/// Renames every bone a mesh is bound to
+fn rename_bone_bindings(elements: &mut [Element], name: &'static str) {
+    for element in elements {
+        match &mut element.element {
+            ElementType::String(value) if element.name == "BoneName" => *value = name.into(),
+            ElementType::Reference(children) | ElementType::Inline(children) => rename_bone_bindings(children, name),
+            ElementType::ReferenceToArray(items) | ElementType::ArrayOfReferences(items) => {
+                items.iter_mut().for_each(|item| rename_bone_bindings(item, name))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_unknown_bone_gltf() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let mut granny_file = GrannyFile::load_from_bytes(data).unwrap();
+    rename_bone_bindings(&mut granny_file.root_elements, "missing");
+
+    assert!(matches!(to_gltf(&granny_file, "suzanne_le.bin"), Err(GrannyError::UnknownBone(name)) if name == "missing"));
+}
+
+#[test]
+fn test_static_meshes_gltf() {
+    let data = include_bytes!("../assets/prova.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let (json, _) = to_gltf(&granny_file, "prova.bin").unwrap();
+    assert_snapshot("prova.gltf", &json);
+    assert!(!json.contains("skins"));
+}
+
+#[test]
+fn test_suzanne_glb() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let glb = to_glb(&granny_file).unwrap();
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32::from_le_bytes(glb[4..8].try_into().unwrap()), 2);
+    assert_eq!(u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize, glb.len());
+
+    let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+    assert!(glb[20..20 + json_length].starts_with(br#"{"asset":{"version":"2.0""#));
+
+    let bin = &glb[20 + json_length..];
+    assert_eq!(&bin[4..8], b"BIN\0");
+    assert_eq!(u32::from_le_bytes(bin[0..4].try_into().unwrap()) as usize, bin.len() - 8);
+}
\ No newline at end of file
@@ -1,4 +1,5 @@
-use opengr2::{GrannyFile, GrannyResolve};
+use std::borrow::Cow;
+use opengr2::{GrannyError, GrannyFile, GrannyResolve, LazyGrannyFile, Verification};
 use opengr2::parser::ElementType;
 
 fn test_suzanne(granny_file: &GrannyFile) {
@@ -6,7 +7,7 @@ fn test_suzanne(granny_file: &GrannyFile) {
     assert_eq!(art_tool_name.name, "FromArtToolName");
 
     let art_tool_name_element = &art_tool_name.element;
-    assert_eq!(*art_tool_name_element, ElementType::String("3D Studio MAX".to_string()));
+    assert_eq!(*art_tool_name_element, ElementType::String("3D Studio MAX".into()));
 
     let meshes = granny_file.find_element("Meshes").unwrap();
     assert_eq!(meshes.name, "Meshes");
@@ -18,10 +19,10 @@ fn test_suzanne(granny_file: &GrannyFile) {
         let mesh = &meshes[0];
 
         let name = mesh.resolve("Name").unwrap();
-        assert_eq!(name.element, ElementType::String("default".to_string()));
+        assert_eq!(name.element, ElementType::String("default".into()));
 
         let vertex_data = mesh.resolve("PrimaryVertexData.Vertices").unwrap();
-        if let ElementType::ArrayOfReferences(vertices) = &vertex_data.element {
+        if let ElementType::ReferenceToVariantArray(vertices) = &vertex_data.element {
             assert_eq!(vertices.len(), 590);
         } else {
             panic!("Unexpected element type of Meshes[0].PrimaryVertexData.Vertices")
@@ -29,6 +30,28 @@ fn test_suzanne(granny_file: &GrannyFile) {
     } else {
         panic!("Unexpected element type of meshes")
     }
+
+    let indices = granny_file.find_element("Meshes[0].PrimaryTopology.Indices").unwrap();
+    if let ElementType::ReferenceToArray(indices) = &indices.element {
+        assert_eq!(indices.len(), 2904);
+    } else {
+        panic!("Unexpected element type of Meshes[0].PrimaryTopology.Indices")
+    }
+
+    let index = granny_file.find_element("Meshes[0].PrimaryTopology.Indices[0].Int32").unwrap();
+    assert_eq!(index.element, ElementType::I32(46));
+
+    let component_name = granny_file.find_element("Meshes[0].PrimaryVertexData.VertexComponentNames[1].String").unwrap();
+    assert_eq!(component_name.element, ElementType::String("Normal".into()));
+
+    let bone_name = granny_file.find_element("Skeletons[0].Bones[0].Name").unwrap();
+    assert_eq!(bone_name.element, ElementType::String("default".into()));
+    assert!(granny_file.find_element("Skeletons[0].Bones[1].Name").is_none());
+    assert!(granny_file.find_element("Skeletons[0].Bones[0]").is_none());
+
+    let first_position = granny_file.find_element("Meshes[0].PrimaryVertexData.Vertices[0].Position").unwrap();
+    let second_position = granny_file.find_element("Meshes[0].PrimaryVertexData.Vertices[1].Position").unwrap();
+    assert_ne!(first_position.element, second_position.element);
 }
 
 #[test]
@@ -76,11 +99,185 @@ fn test_textured_external() {
 
         let texture = materials[0].resolve("Texture").unwrap();
         if let ElementType::Reference(texture) = &texture.element {
-            assert_eq!(texture.resolve("FromFileName").map(|e| &e.element), Some(&ElementType::String("texture.png".to_string())));
+            assert_eq!(texture.resolve("FromFileName").map(|e| &e.element), Some(&ElementType::String("texture.png".into())));
+            assert_eq!(texture.resolve("Layout.BytesPerPixel").map(|e| &e.element), Some(&ElementType::I32(4)));
+            assert_eq!(
+                texture.resolve("Layout.BitsForComponent").map(|e| &e.element),
+                Some(&ElementType::Array(vec![ElementType::I32(8), ElementType::I32(8), ElementType::I32(8), ElementType::I32(8)]))
+            );
         } else {
             panic!("Texture on Material#0 is from the wrong type")
         }
     } else {
         panic!("Materials is from the wrong type")
     }
+}
+
+fn test_suzanne_variants(granny_file: &GrannyFile) {
+    let extended_data = granny_file.find_element("ExporterInfo.ExtendedData").unwrap();
+    assert_eq!(extended_data.element, ElementType::Variant { type_name: None, elements: vec![] });
+
+    let materials = granny_file.find_element("Materials").unwrap();
+    if let ElementType::ArrayOfReferences(materials) = &materials.element {
+        let extended_data = materials[0].resolve("ExtendedData").unwrap();
+        if let ElementType::Variant { type_name, .. } = &extended_data.element {
+            assert_eq!(type_name.as_deref(), Some("Shader Type"));
+        } else {
+            panic!("ExtendedData on Material#0 is from the wrong type")
+        }
+
+        let shader_name = materials[0].resolve("ExtendedData.Shader Name").unwrap();
+        assert_eq!(shader_name.element, ElementType::String("Blinn".into()));
+    } else {
+        panic!("Materials is from the wrong type")
+    }
+}
+
+#[test]
+fn test_variants() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    test_suzanne_variants(&GrannyFile::load_from_bytes(data).unwrap());
+
+    let data = include_bytes!("../assets/suzanne_be64.gr2");
+    test_suzanne_variants(&GrannyFile::load_from_bytes(data).unwrap());
+}
+
+#[test]
+fn test_borrowed() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    // Names and strings of uncompressed sectors point into the loaded data
+    let art_tool_name = granny_file.find_element("ArtToolInfo.FromArtToolName").unwrap();
+    let ElementType::String(Cow::Borrowed(value)) = &art_tool_name.element else {
+        panic!("ArtToolInfo.FromArtToolName isn't borrowed")
+    };
+    assert!(data.as_ptr_range().contains(&value.as_ptr()));
+    assert!(matches!(art_tool_name.name, Cow::Borrowed(_)));
+
+    let owned: GrannyFile<'static> = GrannyFile::load_from_bytes(data).unwrap().into_owned();
+    assert_eq!(owned.root_elements, granny_file.root_elements);
+}
+
+#[test]
+fn test_unsupported_compression() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    // Mark the first sector as Oodle-0 compressed
+    data[104..108].copy_from_slice(&1u32.to_le_bytes());
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data), Err(GrannyError::UnsupportedCompression(1))));
+}
+
+#[test]
+fn test_bad_magic() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    data[0] = 0x00;
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data), Err(GrannyError::BadMagic)));
+}
+
+#[test]
+fn test_truncated_header() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data[..20]), Err(GrannyError::TruncatedHeader)));
+    assert!(matches!(GrannyFile::load_from_bytes(&data[..120]), Err(GrannyError::TruncatedHeader)));
+}
+
+#[test]
+fn test_truncated_data() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data[..20000]), Err(GrannyError::TruncatedData)));
+}
+
+#[test]
+fn test_verification() {
+    let files = [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice(),
+        include_bytes!("../assets/prova.gr2").as_slice()
+    ];
+
+    for data in files {
+        let granny_file = GrannyFile::load_from_bytes_with(data, Verification::Strict).unwrap();
+        assert!(granny_file.warnings.is_empty());
+    }
+}
+
+#[test]
+fn test_crc_mismatch() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    // Change a vertex position at the start of sector 1
+    data[5068] ^= 0xFF;
+
+    assert!(matches!(GrannyFile::load_from_bytes_with(&data, Verification::Strict), Err(GrannyError::CrcMismatch { expected: 0xD06B0D62, .. })));
+
+    let granny_file = GrannyFile::load_from_bytes_with(&data, Verification::Lenient).unwrap();
+    assert!(matches!(granny_file.warnings.as_slice(), [GrannyError::CrcMismatch { expected: 0xD06B0D62, .. }]));
+
+    // Parsing a lazy file keeps its warnings
+    let granny_file = LazyGrannyFile::load_from_bytes_with(&data, Verification::Lenient).unwrap().to_granny_file().unwrap();
+    assert!(matches!(granny_file.warnings.as_slice(), [GrannyError::CrcMismatch { expected: 0xD06B0D62, .. }]));
+}
+
+#[test]
+fn test_size_mismatch() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+
+    assert!(matches!(GrannyFile::load_from_bytes_with(&data[..20000], Verification::Strict), Err(GrannyError::SizeMismatch { expected: 49984, actual: 20000 })));
+
+    let mut padded = data.to_vec();
+    padded.extend([0; 16]);
+    assert!(matches!(GrannyFile::load_from_bytes_with(&padded, Verification::Strict), Err(GrannyError::SizeMismatch { expected: 49984, actual: 50000 })));
+
+    // The CRC32 only covers the data up to the total size
+    let granny_file = GrannyFile::load_from_bytes(&padded).unwrap();
+    assert!(matches!(granny_file.warnings.as_slice(), [GrannyError::SizeMismatch { .. }]));
+}
+
+#[test]
+fn test_sector_out_of_range() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    // Let the root reference point to sector 12
+    data[60..64].copy_from_slice(&12u32.to_le_bytes());
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data), Err(GrannyError::SectorOutOfRange(12))));
+}
+
+#[test]
+fn test_unknown_member_type() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    // Change the type of the first root member which is stored at the start of sector 6
+    data[35564..35568].copy_from_slice(&99u32.to_le_bytes());
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data), Err(GrannyError::UnknownMemberType(99))));
+}
+
+#[test]
+fn test_unsigned_member_type() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    // Change the type of ArtToolInfo.ArtToolMajorRevision from Int32 to UInt32
+    data[36044..36048].copy_from_slice(&20u32.to_le_bytes());
+
+    let granny_file = GrannyFile::load_from_bytes(&data).unwrap();
+    let revision = granny_file.find_element("ArtToolInfo.ArtToolMajorRevision").unwrap();
+    assert_eq!(revision.element, ElementType::U32(20));
+
+    let units_per_meter = granny_file.find_element("ArtToolInfo.UnitsPerMeter").unwrap();
+    assert_eq!(units_per_meter.element, ElementType::F32(39.37008));
+}
+
+#[test]
+fn test_invalid_string() {
+    let mut data = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    let position = data.windows(13).position(|w| w == b"3D Studio MAX").unwrap();
+    data[position] = 0xFF;
+
+    assert!(matches!(GrannyFile::load_from_bytes(&data), Err(GrannyError::InvalidString)));
+}
+
+#[test]
+fn test_missing_file() {
+    assert!(matches!(GrannyFile::load_from_file("assets/does_not_exist.gr2"), Err(GrannyError::Io(_))));
 }
\ No newline at end of file
@@ -0,0 +1,69 @@
+use opengr2::{GrannyError, GrannyFile, LazyGrannyFile};
+use opengr2::parser::{GraphElementType, LazyElementType, ObjectId};
+use opengr2::sector::load_file_info;
+
+/// Replaces the target of the fixup for the pointer at `source` in sector 0
+fn patch_fixup(bytes: &mut [u8], source: u32, old_target: (u32, u32), new_target: (u32, u32)) {
+    let fixup: Vec<u8> = [source, old_target.0, old_target.1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let position = bytes.windows(12).position(|window| window == fixup).unwrap();
+
+    bytes[position + 4..position + 8].copy_from_slice(&new_target.0.to_le_bytes());
+    bytes[position + 8..position + 12].copy_from_slice(&new_target.1.to_le_bytes());
+}
+
+/// Points the extended data of the first material back to the root object
+fn cyclic_suzanne() -> Vec<u8> {
+    let mut bytes = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    let (_, file_info) = load_file_info(&bytes).unwrap();
+
+    // The variant's type pointer is at offset 16 and its data pointer at offset 20 of the material
+    patch_fixup(&mut bytes, 280, (6, 6656), (file_info.type_ref.sector, file_info.type_ref.position));
+    patch_fixup(&mut bytes, 284, (0, 288), (file_info.root_ref.sector, file_info.root_ref.position));
+
+    bytes
+}
+
+#[test]
+fn test_shared_objects() {
+    let bytes = include_bytes!("../assets/suzanne_textured_external.gr2");
+    let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+    let graph = lazy.to_object_graph().unwrap();
+
+    assert_eq!(graph.object(graph.root).offset, 0);
+    assert!(graph.find_cycle().is_none());
+
+    // The mesh is referenced by the root and by the model's mesh binding
+    let Some(GraphElementType::ArrayOfReferences(meshes)) = graph.find_element("Meshes").map(|e| &e.element) else {
+        panic!("Meshes is from the wrong type")
+    };
+    let Some(GraphElementType::Reference(Some(mesh))) = graph.find_element("Models[0].MeshBindings[0].Mesh").map(|e| &e.element) else {
+        panic!("Mesh is from the wrong type")
+    };
+    assert_eq!(meshes[0], Some(*mesh));
+    assert!(graph.reference_counts()[mesh.0] > 1);
+
+    let (sector, offset) = lazy.find_element("Meshes").and_then(|e| match &e.element {
+        LazyElementType::ArrayOfReferences(meshes) => meshes.get(0).unwrap().unwrap().location(),
+        _ => None
+    }).unwrap();
+    assert_eq!(graph.find_object(sector, offset), Some(*mesh));
+}
+
+#[test]
+fn test_cyclic_reference() {
+    let bytes = cyclic_suzanne();
+
+    assert!(matches!(
+        GrannyFile::load_from_bytes(&bytes),
+        Err(GrannyError::CyclicReference { sector: 0, offset: 0 })
+    ));
+
+    // The lazy tree only follows references on access
+    let lazy = LazyGrannyFile::load_from_bytes(&bytes).unwrap();
+    let art_tool_name = lazy.find_element("Materials[0].ExtendedData.ArtToolInfo.FromArtToolName").unwrap();
+    assert!(art_tool_name.to_element().is_ok());
+
+    let graph = lazy.to_object_graph().unwrap();
+    let cycle = graph.find_cycle().unwrap();
+    assert_eq!(cycle, vec![ObjectId(0), graph.find_object(0, 264).unwrap()]);
+}
\ No newline at end of file
@@ -0,0 +1,190 @@
+use opengr2::{GrannyError, GrannyFile, GrannyResolve, LazyGrannyFile};
+use opengr2::parser::{Element, ElementType, LazyElement, LazyElementType, LazyObject};
+use opengr2::sector::load_sectors;
+
+/// Converts the lazy tree by accessing every object like a user would
+fn walk_object<'a>(object: &LazyObject<'a>) -> Vec<Element<'a>> {
+    object.members().unwrap().iter().map(walk_element).collect()
+}
+
+fn walk_element<'a>(element: &LazyElement<'a>) -> Element<'a> {
+    Element { name: element.name.clone(), element: walk_type(&element.element), type_ref: Some(element.type_ref) }
+}
+
+fn walk_type<'a>(element: &LazyElementType<'a>) -> ElementType<'a> {
+    match element {
+        LazyElementType::Reference(object) => ElementType::Reference(walk_object(object)),
+        LazyElementType::Inline(members) => ElementType::Inline(members.iter().map(walk_element).collect()),
+        LazyElementType::ReferenceToArray(array) => ElementType::ReferenceToArray(array.items().unwrap().iter().map(walk_object).collect()),
+        LazyElementType::ArrayOfReferences(array) => ElementType::ArrayOfReferences(array.items().unwrap().iter().map(walk_object).collect()),
+        LazyElementType::ReferenceToVariantArray(array) => ElementType::ReferenceToVariantArray(array.items().unwrap().iter().map(walk_object).collect()),
+        LazyElementType::Variant { type_name, object } => ElementType::Variant { type_name: type_name.clone(), elements: walk_object(object) },
+        LazyElementType::Value(value) => value.clone(),
+        LazyElementType::Array(values) => ElementType::Array(values.iter().map(walk_type).collect())
+    }
+}
+
+#[test]
+fn test_lazy_equals_eager() {
+    let files = [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_textured_external.gr2").as_slice(),
+        include_bytes!("../assets/prova.gr2").as_slice(),
+        include_bytes!("../assets/test1.gr2").as_slice()
+    ];
+
+    for bytes in files {
+        let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+        let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+
+        assert_eq!(lazy.format, granny_file.format);
+        assert_eq!(walk_object(&lazy.root), granny_file.root_elements);
+        assert_eq!(lazy.to_granny_file().unwrap().root_elements, granny_file.root_elements);
+    }
+}
+
+#[test]
+fn test_lazy_lookup() {
+    let bytes = include_bytes!("../assets/suzanne_le.gr2");
+    let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+    let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+    assert!(!lazy.root.is_parsed());
+
+    let art_tool_name = lazy.find_element("ArtToolInfo.FromArtToolName").unwrap();
+    assert!(matches!(&art_tool_name.element, LazyElementType::Value(ElementType::String(name)) if name == "3D Studio MAX"));
+
+    // Only the root and the art tool info were parsed, the meshes weren't touched
+    let Some(LazyElementType::ArrayOfReferences(meshes)) = lazy.find_element("Meshes").map(|e| &e.element) else {
+        panic!("Meshes is from the wrong type")
+    };
+    assert_eq!(meshes.len(), 1);
+    assert!(!meshes.get(0).unwrap().unwrap().is_parsed());
+
+    let path = "Meshes[0].PrimaryVertexData.Vertices[1].Position";
+    let position = lazy.find_element(path).unwrap();
+    assert_eq!(position.to_element().unwrap(), *granny_file.find_element(path).unwrap());
+    assert!(meshes.get(0).unwrap().unwrap().is_parsed());
+
+    let Some(LazyElementType::ReferenceToVariantArray(vertices)) = lazy.find_element("Meshes[0].PrimaryVertexData.Vertices").map(|e| &e.element) else {
+        panic!("Vertices is from the wrong type")
+    };
+    assert_eq!(vertices.len(), 590);
+    assert!(vertices.get(1).unwrap().unwrap().is_parsed());
+    assert!(!vertices.get(2).unwrap().unwrap().is_parsed());
+
+    assert!(lazy.find_element("Meshes[1].Name").is_none());
+    assert!(lazy.find_element("Skeletons[0].Bones[1].Name").is_none());
+    assert!(lazy.root.resolve("Skeletons[0].Bones[0]").is_none());
+    assert!(lazy.root.try_resolve("Missing.Name").unwrap().is_none());
+}
+
+/// Keeps the included file aligned like a buffer from the allocator, so its values can be viewed as slices
+#[repr(C)]
+struct Aligned<B: ?Sized> {
+    _align: [u64; 0],
+    bytes: B
+}
+
+static SUZANNE_LE: &Aligned<[u8]> = &Aligned { _align: [], bytes: *include_bytes!("../assets/suzanne_le.gr2") };
+
+#[test]
+fn test_array_views() {
+    let files = [
+        &SUZANNE_LE.bytes,
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice()
+    ];
+
+    for bytes in files {
+        let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+        let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+
+        let vertices = lazy.find_element("Meshes[0].PrimaryVertexData.Vertices").and_then(LazyElement::array).unwrap();
+        let positions = vertices.view_member::<[f32; 3]>("Position").unwrap();
+        assert_eq!(positions.len(), 590);
+        assert_eq!(positions.get(0), Some([0.4375, -0.765625, 0.164063]));
+        assert!(positions.as_slice().is_none());
+
+        let Some(ElementType::Array(position)) = granny_file.find_element("Meshes[0].PrimaryVertexData.Vertices[589].Position").map(|e| &e.element) else {
+            panic!("Position is from the wrong type")
+        };
+        let expected: Vec<_> = position.iter().map(|value| match value {
+            ElementType::F32(value) => *value,
+            _ => panic!("Position contains a non float")
+        }).collect();
+        assert_eq!(positions.iter().last().unwrap().as_slice(), expected);
+
+        let indices = lazy.find_element("Meshes[0].PrimaryTopology.Indices").and_then(LazyElement::array).unwrap();
+        let indices = indices.view::<u32>().unwrap();
+        assert_eq!(indices.len(), 2904);
+        assert_eq!(indices.get(0), Some(46));
+        assert!(indices.iter().all(|index| index < 590));
+        // Big endian files are viewed in their native copy, which is aligned like any allocation
+        assert!(indices.as_slice().is_some());
+        assert_eq!(indices.to_vec().len(), 2904);
+
+        assert!(matches!(vertices.view_member::<[f32; 2]>("Position"), Err(GrannyError::WrongFieldType(name)) if name == "Position"));
+        assert!(matches!(vertices.view::<f32>(), Err(GrannyError::WrongFieldType(_))));
+        assert!(matches!(vertices.view_member::<u32>("Missing"), Err(GrannyError::MissingField(_))));
+    }
+}
+
+/// Replaces the target of the fixup for the pointer at `source`
+fn patch_fixup(bytes: &mut [u8], source: u32, old_target: (u32, u32), new_target: (u32, u32)) {
+    let fixup: Vec<u8> = [source, old_target.0, old_target.1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let position = bytes.windows(12).position(|window| window == fixup).unwrap();
+
+    bytes[position + 4..position + 8].copy_from_slice(&new_target.0.to_le_bytes());
+    bytes[position + 8..position + 12].copy_from_slice(&new_target.1.to_le_bytes());
+}
+
+#[test]
+fn test_array_out_of_sector() {
+    let mut bytes = include_bytes!("../assets/suzanne_le.gr2").to_vec();
+    let lazy = LazyGrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_le.gr2")).unwrap();
+    let Some(LazyElementType::Reference(topology)) = lazy.find_element("Meshes[0].PrimaryTopology").map(|e| &e.element) else {
+        panic!("PrimaryTopology is from the wrong type")
+    };
+
+    // The count of `Indices` follows the count and pointer of `Groups`
+    let (sector, offset) = topology.location().unwrap();
+    let (_, _, sectors) = load_sectors(&bytes).unwrap();
+    let position = sectors[0].info.data_offset as usize + offset as usize + 8;
+    assert_eq!(sector, 0);
+    assert_eq!(bytes[position..position + 4], 2904u32.to_le_bytes());
+
+    for count in [u32::MAX, 1 << 30] {
+        bytes[position..position + 4].copy_from_slice(&count.to_le_bytes());
+        let lazy = LazyGrannyFile::load_from_bytes(&bytes).unwrap();
+        let indices = lazy.find_element("Meshes[0].PrimaryTopology.Indices").and_then(LazyElement::array).unwrap();
+
+        assert_eq!(indices.len(), count as usize);
+        assert!(matches!(indices.items(), Err(GrannyError::TruncatedData)));
+        assert!(matches!(indices.view::<u32>(), Err(GrannyError::TruncatedData)));
+    }
+}
+
+#[test]
+fn test_cyclic_inline_type() {
+    let mut bytes = include_bytes!("../assets/suzanne_textured_external.gr2").to_vec();
+    let lazy = LazyGrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_textured_external.gr2")).unwrap();
+    let tree = lazy.type_tree().unwrap();
+
+    // Inline the texture type into its own `Layout` member
+    let texture = tree.resolve("Textures").unwrap().children.unwrap();
+    let layout_index = tree.get(&texture).unwrap().members.iter().position(|member| member.name == "Layout").unwrap();
+    let layout = tree.get(&texture).unwrap().members[layout_index].children.unwrap();
+    let source = texture.position + layout_index as u32 * 32 + 8;
+    patch_fixup(&mut bytes, source, (layout.sector, layout.position), (texture.sector, texture.position));
+
+    let lazy = LazyGrannyFile::load_from_bytes(&bytes).unwrap();
+    let textures = lazy.find_element("Textures").and_then(LazyElement::array).unwrap();
+    assert!(matches!(
+        textures.get(0).unwrap().unwrap().members(),
+        Err(GrannyError::CyclicReference { sector, offset }) if (sector, offset) == (texture.sector, texture.position)
+    ));
+}
\ No newline at end of file
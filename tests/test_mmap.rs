@@ -0,0 +1,30 @@
+#![cfg(feature = "mmap")]
+
+use std::borrow::Cow;
+use opengr2::{GrannyError, GrannyFile, GrannyResolve, MappedFile, Verification};
+use opengr2::parser::ElementType;
+
+#[test]
+fn test_mapped_file() {
+    let mapped = MappedFile::open("assets/suzanne_le.gr2").unwrap();
+    assert_eq!(mapped.bytes(), include_bytes!("../assets/suzanne_le.gr2"));
+
+    let granny_file = mapped.load_with(Verification::Strict).unwrap();
+
+    // Strings of uncompressed sectors are parsed directly from the mapping
+    let art_tool_name = granny_file.find_element("ArtToolInfo.FromArtToolName").unwrap();
+    let ElementType::String(Cow::Borrowed(value)) = &art_tool_name.element else {
+        panic!("ArtToolInfo.FromArtToolName isn't borrowed")
+    };
+    assert_eq!(*value, "3D Studio MAX");
+    assert!(mapped.bytes().as_ptr_range().contains(&value.as_ptr()));
+
+    let loaded = GrannyFile::load_from_file("assets/suzanne_le.gr2").unwrap();
+    assert_eq!(loaded.root_elements, granny_file.root_elements);
+    assert!(loaded.root_elements.resolve("Meshes[0].PrimaryVertexData.Vertices[1].Position").is_some());
+}
+
+#[test]
+fn test_missing_file() {
+    assert!(matches!(MappedFile::open("assets/missing.gr2"), Err(GrannyError::Io(_))));
+}
\ No newline at end of file
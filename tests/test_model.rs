@@ -0,0 +1,260 @@
+use opengr2::{GrannyError, GrannyFile};
+use opengr2::model::{Animation, Bone, Curve, CurveFormat, FileInfo, IDENTITY_4X4, multiply_4x4, Skeleton, TrackGroup, TransformTrack};
+use opengr2::parser::{Element, ElementType, Transform};
+
+fn test_suzanne(file_info: &FileInfo) {
+    let art_tool_info = file_info.art_tool_info.as_ref().unwrap();
+    assert_eq!(art_tool_info.from_art_tool_name, "3D Studio MAX");
+    assert_eq!(art_tool_info.units_per_meter, 39.37008);
+
+    let exporter_info = file_info.exporter_info.as_ref().unwrap();
+    assert_eq!(exporter_info.exporter_major_revision, 2);
+    assert_eq!(exporter_info.exporter_minor_revision, 11);
+
+    assert_eq!(file_info.skeletons.len(), 1);
+    let bone = &file_info.skeletons[0].bones[0];
+    assert_eq!(bone.name, "default");
+    assert_eq!(bone.parent_index, -1);
+    assert_eq!(bone.inverse_world_4x4[3], [0.0, 0.0, 0.0, 1.0]);
+
+    assert_eq!(file_info.meshes.len(), 1);
+    let mesh = &file_info.meshes[0];
+    assert_eq!(mesh.name, "default");
+    assert_eq!(mesh.primary_vertex_data.vertices.len(), 590);
+    assert_eq!(mesh.primary_vertex_data.vertex_component_names[..2], ["Position", "Normal"]);
+    assert_eq!(mesh.primary_topology.indices.len(), 2904);
+    assert!(mesh.primary_topology.indices16.is_empty());
+    assert_eq!(mesh.primary_topology.groups[0].tri_count, 968);
+    assert_eq!(mesh.bone_bindings[0].bone_name, "default");
+
+    assert_eq!(file_info.models.len(), 1);
+    let model = &file_info.models[0];
+    assert_eq!(model.skeleton.as_ref(), Some(&file_info.skeletons[0]));
+    assert_eq!(model.mesh_bindings, file_info.meshes);
+
+    assert_eq!(file_info.animations.len(), 1);
+    let animation = &file_info.animations[0];
+    assert_eq!(animation.duration, 3.3333335);
+    assert_eq!(animation.oversampling, 2.0);
+    assert_eq!(animation.track_groups, file_info.track_groups);
+
+    let track = &animation.track_groups[0].transform_tracks[0];
+    assert_eq!(track.name, "default");
+    assert_eq!(track.position_curve.type_name.as_deref(), Some("CurveDataHeader_DaIdentity"));
+    assert_eq!(track.orientation_curve.decode().unwrap().evaluate(1.0), vec![0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(track.scale_shear_curve.decode().unwrap().dimension, 9);
+}
+
+#[test]
+fn test_suzanne_models() {
+    for data in [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice()
+    ] {
+        let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+        test_suzanne(&granny_file.file_info().unwrap());
+    }
+}
+
+#[test]
+fn test_textured_external_materials() {
+    let data = include_bytes!("../assets/suzanne_textured_external.gr2");
+    let file_info = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap();
+
+    test_suzanne(&file_info);
+
+    assert_eq!(file_info.textures.len(), 1);
+    assert_eq!(file_info.textures[0].from_file_name, "texture.png");
+    assert_eq!(file_info.textures[0].layout.bits_for_component, [8, 8, 8, 8]);
+    assert!(file_info.textures[0].images.is_empty());
+
+    let material = &file_info.meshes[0].material_bindings[0];
+    assert_eq!(material.name, "Material #25");
+    assert_eq!(material.maps[0].usage, "Diffuse Color");
+
+    let map = material.maps[0].map.as_ref().unwrap();
+    assert_eq!(map.texture.as_ref(), Some(&file_info.textures[0]));
+}
+
+#[test]
+fn test_format_6() {
+    let data = include_bytes!("../assets/prova.gr2");
+    let file_info = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap();
+
+    assert_eq!(file_info.art_tool_info.unwrap().art_tool_pointer_size, None);
+    assert_eq!(file_info.meshes.len(), 5);
+    assert_eq!(file_info.meshes[0].name, "Box05");
+    assert_eq!(file_info.meshes[0].primary_topology.indices.len(), 36);
+    assert!(file_info.models.is_empty());
+}
+
+#[test]
+fn test_missing_field() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let mut granny_file = GrannyFile::load_from_bytes(data).unwrap();
+    granny_file.root_elements.retain(|e| e.name != "Meshes");
+
+    assert!(matches!(granny_file.file_info(), Err(GrannyError::MissingField(name)) if name == "Meshes"));
+}
+
+#[test]
+fn test_wrong_field_type() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let mut granny_file = GrannyFile::load_from_bytes(data).unwrap();
+    let from_file_name = granny_file.root_elements.iter_mut().find(|e| e.name == "FromFileName").unwrap();
+    from_file_name.element = ElementType::I32(0);
+
+    assert!(matches!(granny_file.file_info(), Err(GrannyError::WrongFieldType(name)) if name == "FromFileName"));
+}
+
+#[test]
+fn test_mesh_geometry() {
+    for data in [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_textured_external.gr2").as_slice()
+    ] {
+        let file_info = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap();
+        let geometry = file_info.meshes[0].geometry().unwrap();
+
+        assert_eq!(geometry.positions.len(), 590);
+        assert_eq!(geometry.positions[0], [0.4375, -0.765625, 0.164063]);
+        assert_eq!(geometry.normals.len(), 590);
+        assert_eq!(geometry.uvs.len(), 1);
+        assert_eq!(geometry.uvs[0].len(), 590);
+        assert!(geometry.tangents.is_empty());
+        assert!(geometry.colors.is_empty());
+        assert!(geometry.bone_weights.is_empty());
+
+        assert_eq!(geometry.indices.len(), 2904);
+        assert_eq!(geometry.indices[0], 46);
+        assert!(geometry.indices.iter().all(|&index| index < 590));
+    }
+}
+
+#[test]
+fn test_mesh_geometry_indices16() {
+    let data = include_bytes!("../assets/suzanne_le.gr2");
+    let mut mesh = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap().meshes.remove(0);
+
+    let topology = &mut mesh.primary_topology;
+    topology.indices16 = topology.indices.iter().map(|&index| index as u16).collect();
+    topology.indices.clear();
+
+    let geometry = mesh.geometry().unwrap();
+    assert_eq!(geometry.indices.len(), 2904);
+    assert_eq!(geometry.indices[0], 46);
+}
+
+#[test]
+fn test_skeleton_world_matrices() {
+    let data = include_bytes!("../assets/suzanne_le64.gr2");
+    let skeleton = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap().skeletons.remove(0);
+
+    assert_eq!(skeleton.find_bone("default"), Some(0));
+    assert_eq!(skeleton.world_matrices(), vec![IDENTITY_4X4]);
+    assert_eq!(multiply_4x4(&skeleton.world_matrices()[0], &skeleton.bones[0].inverse_world_4x4), IDENTITY_4X4);
+}
+
+#[test]
+fn test_skeleton_hierarchy() {
+    let bone = |name: &str, parent_index: i32, translation: [f32; 3]| Bone {
+        name: name.to_string(),
+        parent_index,
+        local_transform: Transform { flags: Transform::HAS_POSITION, translation, ..Transform::identity() },
+        inverse_world_4x4: IDENTITY_4X4,
+        lod_error: None
+    };
+
+    let skeleton = Skeleton {
+        name: "skeleton".to_string(),
+        bones: vec![bone("root", -1, [1.0, 0.0, 0.0]), bone("child", 0, [0.0, 2.0, 0.0]), bone("grandchild", 1, [0.0, 0.0, 3.0])],
+        lod_type: None
+    };
+
+    let world = skeleton.world_matrices();
+    assert_eq!(world[0][3], [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(world[1][3], [1.0, 2.0, 0.0, 1.0]);
+    assert_eq!(world[2][3], [1.0, 2.0, 3.0, 1.0]);
+}
+
+#[test]
+fn test_animation_identity_pose() {
+    let data = include_bytes!("../assets/suzanne_textured_external.gr2");
+    let file_info = GrannyFile::load_from_bytes(data).unwrap().file_info().unwrap();
+
+    let animation = &file_info.animations[0];
+    let skeleton = &file_info.skeletons[0];
+    let track_group = animation.find_track_group(&file_info.models[0].name).unwrap();
+
+    let pose = animation.sample(track_group, skeleton, 1.0).unwrap();
+    assert_eq!(pose.local_transforms[0].flags, Transform::HAS_POSITION | Transform::HAS_ORIENTATION | Transform::HAS_SCALE_SHEAR);
+    assert_eq!(pose.world_matrices, vec![IDENTITY_4X4]);
+}
+
+#[test]
+fn test_animation_keyframes() {
+    let element = |name: &str, element: ElementType<'static>| Element { name: name.to_string().into(), element, type_ref: None };
+    let keyframes = |dimension: i16, controls: &[f32]| Curve {
+        type_name: Some("CurveDataHeader_DaKeyframes32f".to_string()),
+        elements: vec![
+            element("CurveDataHeader_DaKeyframes32f", ElementType::Inline(vec![
+                element("Format", ElementType::U8(CurveFormat::DaKeyframes32f as u8)),
+                element("Degree", ElementType::U8(1))
+            ])),
+            element("Dimension", ElementType::I16(dimension)),
+            element("Controls", ElementType::ReferenceToArray(controls.iter().map(|&v| vec![element("Real32", ElementType::F32(v))]).collect()))
+        ]
+    };
+    let empty = Curve { type_name: None, elements: vec![] };
+
+    let bone = |name: &str, parent_index: i32| Bone {
+        name: name.to_string(),
+        parent_index,
+        local_transform: Transform { flags: Transform::HAS_POSITION, translation: [0.0, 1.0, 0.0], ..Transform::identity() },
+        inverse_world_4x4: IDENTITY_4X4,
+        lod_error: None
+    };
+    let skeleton = Skeleton { name: "skeleton".to_string(), bones: vec![bone("root", -1), bone("child", 0)], lod_type: None };
+
+    let track_group = TrackGroup {
+        name: "skeleton".to_string(),
+        transform_tracks: vec![TransformTrack {
+            name: "child".to_string(),
+            flags: None,
+            orientation_curve: empty.clone(),
+            position_curve: keyframes(3, &[0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 4.0, 0.0, 0.0]),
+            scale_shear_curve: empty
+        }],
+        initial_placement: Transform::identity(),
+        accumulation_flags: 0,
+        loop_translation: [0.0; 3]
+    };
+
+    let animation = Animation {
+        name: "animation".to_string(),
+        duration: 1.0,
+        time_step: 1.0,
+        oversampling: 2.0,
+        track_groups: vec![track_group.clone()],
+        default_loop_count: None,
+        flags: None
+    };
+
+    let pose = animation.sample(&track_group, &skeleton, 0.75).unwrap();
+    assert_eq!(pose.local_transforms[0], skeleton.bones[0].local_transform);
+    assert_eq!(pose.local_transforms[1].translation, [3.0, 0.0, 0.0]);
+    assert_eq!(pose.world_matrices[1][3], [3.0, 1.0, 0.0, 1.0]);
+
+    // Times after the end of the animation are clamped
+    let pose = animation.sample(&track_group, &skeleton, 5.0).unwrap();
+    assert_eq!(pose.local_transforms[1].translation, [4.0, 0.0, 0.0]);
+
+    // The sampler decodes the curves once and returns the same poses
+    let sampler = animation.sampler(&track_group, &skeleton).unwrap();
+    for time in [0.0, 0.25, 0.75, 5.0] {
+        assert_eq!(sampler.sample(time), animation.sample(&track_group, &skeleton, time).unwrap());
+    }
+}
\ No newline at end of file
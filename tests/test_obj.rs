@@ -0,0 +1,52 @@
+use opengr2::GrannyFile;
+use opengr2::export::obj::to_obj;
+
+fn count(obj: &str, prefix: &str) -> usize {
+    obj.lines().filter(|line| line.starts_with(prefix)).count()
+}
+
+#[test]
+fn test_textured_external_obj() {
+    let data = include_bytes!("../assets/suzanne_textured_external.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let (obj, mtl) = to_obj(&granny_file, "suzanne.mtl").unwrap();
+    assert!(obj.starts_with("mtllib suzanne.mtl\no default\n"));
+    assert_eq!(count(&obj, "v "), 590);
+    assert_eq!(count(&obj, "vt "), 590);
+    assert_eq!(count(&obj, "vn "), 590);
+    assert_eq!(count(&obj, "f "), 968);
+    assert!(obj.contains("usemtl Material__25\nf 47/47/47 "));
+
+    assert_eq!(mtl, "newmtl Material__25\nKd 1 1 1\nmap_Kd texture.png\n");
+}
+
+#[test]
+fn test_suzanne_obj() {
+    let le = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_le.gr2")).unwrap();
+    let be = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_be64.gr2")).unwrap();
+
+    let (obj, _) = to_obj(&le, "suzanne.mtl").unwrap();
+    assert_eq!(count(&obj, "v "), 590);
+    assert!(obj.contains("\nf 47/47/47 "));
+
+    assert_eq!(to_obj(&be, "suzanne.mtl").unwrap().0, obj);
+}
+
+#[test]
+fn test_multiple_meshes_obj() {
+    let data = include_bytes!("../assets/prova.gr2");
+    let granny_file = GrannyFile::load_from_bytes(data).unwrap();
+
+    let (obj, _) = to_obj(&granny_file, "prova.mtl").unwrap();
+    assert_eq!(count(&obj, "o "), 5);
+    assert!(obj.contains("o Box05\n"));
+
+    // Indices of later meshes are offset by the vertices of the previous meshes
+    let first_vertices = obj.split("\no ").nth(1).unwrap().lines().filter(|line| line.starts_with("v ")).count();
+    let second_faces: Vec<usize> = obj.split("\no ").nth(2).unwrap().lines()
+        .filter(|line| line.starts_with("f "))
+        .flat_map(|line| line[2..].split(' ').map(|index| index.split('/').next().unwrap().parse().unwrap()).collect::<Vec<_>>())
+        .collect();
+    assert!(second_faces.iter().all(|&index| index > first_vertices));
+}
\ No newline at end of file
@@ -1,6 +1,6 @@
 use nom::number::Endianness;
-use opengr2::parser::{parse_element, parse_file_info, parse_header, parse_sector_info};
-use opengr2::sector::load_sector;
+use opengr2::parser::{Marshall, parse_element, parse_file_info, parse_header, parse_sector_info};
+use opengr2::sector::{load_sector, load_sectors, native_sector_data};
 
 fn parse_data(bytes: &[u8]) {
     let (input, header) = parse_header(bytes).unwrap();
@@ -22,7 +22,7 @@ fn parse_data(bytes: &[u8]) {
         println!("Sector {}:", i);
         println!("{:?}", sector);
 
-        sectors.push(load_sector(bytes, endianness, sector));
+        sectors.push(load_sector(bytes, endianness, sector).unwrap());
 
         input = next_input;
     }
@@ -36,6 +36,8 @@ fn parse_data(bytes: &[u8]) {
         file_info.root_ref.position,
         file_info.type_ref.position
     ).unwrap();
+
+    assert!(!root.is_empty());
 }
 
 #[test]
@@ -64,4 +66,40 @@ fn test_parser_integration_be_7_64bits() {
     let bytes = include_bytes!("../assets/suzanne_be64.gr2");
 
     parse_data(bytes);
+}
+
+#[test]
+fn test_marshall_table() {
+    for bytes in [include_bytes!("../assets/suzanne_le.gr2").as_slice(), include_bytes!("../assets/suzanne_be.gr2").as_slice()] {
+        let (_, _, sectors) = load_sectors(bytes).unwrap();
+
+        // The three identity curve headers contain 8 and 16 bit members
+        assert_eq!(sectors[0].marshall_table, [4592, 4596, 4600].map(|offset| Marshall { count: 1, offset, type_sector: 6, type_offset: 9344 }));
+        assert!(sectors[1].marshall_table.is_empty());
+    }
+}
+
+#[test]
+fn test_native_sector_data() {
+    // The main sectors of the 64 bit files are laid out differently, only compare the vertices and indices there
+    let files = [
+        (include_bytes!("../assets/suzanne_le.gr2").as_slice(), include_bytes!("../assets/suzanne_be.gr2").as_slice(), 0..3),
+        (include_bytes!("../assets/suzanne_le64.gr2").as_slice(), include_bytes!("../assets/suzanne_be64.gr2").as_slice(), 1..3)
+    ];
+
+    for (le, be, sectors) in files {
+        let (le_header, _, le_sectors) = load_sectors(le).unwrap();
+        let (be_header, _, be_sectors) = load_sectors(be).unwrap();
+
+        // Both files were exported from the same scene, only the byte order differs
+        for sector in sectors {
+            assert_eq!(
+                native_sector_data(&le_sectors, sector, Endianness::Little, le_header.bits_64).unwrap(),
+                native_sector_data(&be_sectors, sector, Endianness::Big, be_header.bits_64).unwrap()
+            );
+        }
+
+        let vertices = native_sector_data(&be_sectors, 1, Endianness::Big, be_header.bits_64).unwrap();
+        assert_eq!(f32::from_ne_bytes(vertices[0..4].try_into().unwrap()), 0.4375);
+    }
 }
\ No newline at end of file
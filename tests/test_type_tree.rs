@@ -0,0 +1,113 @@
+use opengr2::{GrannyFile, LazyGrannyFile};
+use opengr2::parser::{ElementType, LazyElementType, MemberType};
+use opengr2::sector::load_file_info;
+
+#[test]
+fn test_type_tree() {
+    let files = [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice()
+    ];
+
+    let mut schemas = Vec::new();
+    for bytes in files {
+        let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+        let tree = lazy.type_tree().unwrap();
+
+        let (_, file_info) = load_file_info(bytes).unwrap();
+        assert_eq!(tree.root, file_info.type_ref);
+
+        // The root type lists the members of the root object in order
+        let names: Vec<_> = tree.root_type().members.iter().map(|member| member.name.clone()).collect();
+        let elements: Vec<_> = lazy.root.members().unwrap().iter().map(|element| element.name.clone()).collect();
+        assert_eq!(names, elements);
+
+        let art_tool_info = tree.resolve("ArtToolInfo").unwrap();
+        assert_eq!(art_tool_info.member_type, MemberType::Reference);
+        assert!(tree.get(&art_tool_info.children.unwrap()).is_some());
+
+        let name = tree.resolve("Meshes[0].Name").unwrap();
+        assert_eq!(name.member_type, MemberType::String);
+
+        // The vertex type is stored next to the vertices
+        let vertices = tree.resolve("Meshes[0].PrimaryVertexData.Vertices").unwrap();
+        assert_eq!(vertices.member_type, MemberType::ReferenceToVariantArray);
+        assert_eq!(vertices.children, None);
+        assert!(tree.resolve("Meshes[0].PrimaryVertexData.Vertices[0].Position").is_none());
+        assert!(tree.resolve("Meshes[0].Missing").is_none());
+
+        let vertex_array = lazy.find_element("Meshes[0].PrimaryVertexData.Vertices").and_then(|e| e.array()).unwrap();
+        let items = vertex_array.items().unwrap();
+        let vertex = items[0].type_definition().unwrap().unwrap();
+        assert_eq!(items[0].type_ref(), vertex_array.type_ref());
+        let position = vertex.member("Position").unwrap();
+        assert_eq!((position.member_type, position.array_size), (MemberType::Real32, 3));
+
+        // Every type is listed once, so the schema can be compared between files
+        let mut schema: Vec<_> = tree.types.values()
+            .map(|definition| definition.members.iter().map(|member| format!("{}: {:?}[{}]", member.name, member.member_type, member.array_size)).collect::<Vec<_>>())
+            .collect();
+        schema.sort();
+        schemas.push(schema);
+
+        let printed = tree.to_string();
+        assert!(printed.contains(&format!("type {}:{} (root)", tree.root.sector, tree.root.position)));
+        assert!(printed.contains("    ArtToolInfo: Reference -> "));
+    }
+
+    assert!(schemas.iter().all(|schema| *schema == schemas[0]));
+}
+
+#[test]
+fn test_variant_type() {
+    let bytes = include_bytes!("../assets/suzanne_le.gr2");
+    let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+    let tree = lazy.type_tree().unwrap();
+
+    let extended_data = tree.resolve("Materials[0].ExtendedData").unwrap();
+    assert_eq!(extended_data.member_type, MemberType::VariantReference);
+
+    let Some(LazyElementType::Variant { object, .. }) = lazy.find_element("Materials[0].ExtendedData").map(|e| &e.element) else {
+        panic!("ExtendedData is from the wrong type")
+    };
+    let definition = object.type_definition().unwrap().unwrap();
+    assert_eq!(definition.members[0].name, "Shader Type");
+
+    let variant_tree = object.type_tree().unwrap().unwrap();
+    assert_eq!(variant_tree.root_type(), &definition);
+    assert!(tree.get(&variant_tree.root).is_none());
+}
+
+#[test]
+fn test_element_types() {
+    let bytes = include_bytes!("../assets/suzanne_le.gr2");
+    let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+    let tree = granny_file.type_tree().unwrap();
+
+    let lazy = LazyGrannyFile::load_from_bytes(bytes).unwrap();
+    assert_eq!(tree.root, lazy.type_ref);
+
+    // Elements keep the location of their member definition
+    let name = granny_file.find_element("Meshes[0].Name").unwrap();
+    assert_eq!(granny_file.member_definition(name), tree.resolve("Meshes[0].Name"));
+    assert_eq!(lazy.find_element("Meshes[0].Name").unwrap().type_ref, name.type_ref.unwrap());
+
+    // The types of variants are in the tree as well
+    let Some(ElementType::ReferenceToVariantArray(vertices)) = granny_file.find_element("Meshes[0].PrimaryVertexData.Vertices").map(|e| &e.element) else {
+        panic!("Vertices is from the wrong type")
+    };
+    let position = granny_file.member_definition(&vertices[0][0]).unwrap();
+    assert_eq!((position.name.as_ref(), position.member_type, position.array_size), ("Position", MemberType::Real32, 3));
+
+    let Some(ElementType::Variant { elements, .. }) = granny_file.find_element("Materials[0].ExtendedData").map(|e| &e.element) else {
+        panic!("ExtendedData is from the wrong type")
+    };
+    assert_eq!(granny_file.member_definition(&elements[0]).unwrap().name, "Shader Type");
+    assert!(lazy.type_tree().unwrap().types.len() < tree.types.len());
+
+    // Owned files keep their types and the parsed lazy file finds the same ones
+    assert_eq!(lazy.to_granny_file().unwrap().type_tree(), Some(tree));
+    assert_eq!(GrannyFile::load_from_bytes(bytes).unwrap().into_owned().type_tree(), Some(tree));
+}
\ No newline at end of file
@@ -0,0 +1,160 @@
+use opengr2::{FileFormat, GrannyError, GrannyFile, Verification};
+use opengr2::parser::{Element, ElementType, MemberType, parse_file_info, parse_header, TypeTree};
+use nom::number::Endianness;
+
+fn element(name: &str, element: ElementType<'static>) -> Element<'static> {
+    Element { name: name.to_string().into(), element, type_ref: None }
+}
+
+fn assert_round_trip(bytes: &[u8], format: FileFormat) {
+    let granny_file = GrannyFile::load_from_bytes(bytes).unwrap();
+
+    let written = granny_file.save_to_bytes(format).unwrap();
+    let loaded = GrannyFile::load_from_bytes_with(&written, Verification::Strict).unwrap();
+    assert_eq!(loaded.root_elements, granny_file.root_elements);
+    assert_eq!(loaded.format, format);
+    assert_eq!(loaded.tag, granny_file.tag);
+
+    // Writing is deterministic and the written type tree describes the data the same way
+    assert_eq!(loaded.save_to_bytes(format).unwrap(), written);
+}
+
+#[test]
+fn test_round_trip() {
+    let files = [
+        include_bytes!("../assets/suzanne_le.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_le64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_be64.gr2").as_slice(),
+        include_bytes!("../assets/suzanne_textured_external.gr2").as_slice(),
+        include_bytes!("../assets/prova.gr2").as_slice(),
+        include_bytes!("../assets/test1.gr2").as_slice()
+    ];
+
+    for bytes in files {
+        let format = GrannyFile::load_from_bytes(bytes).unwrap().format;
+        assert_round_trip(bytes, format);
+    }
+}
+
+#[test]
+fn test_header() {
+    let granny_file = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_be64.gr2")).unwrap();
+    let written = granny_file.save_to_bytes(FileFormat::BIG_ENDIAN_64).unwrap();
+
+    let (data, header) = parse_header(&written).unwrap();
+    assert!(header.big_endian);
+    assert!(header.bits_64);
+
+    let (_, file_info) = parse_file_info(Endianness::Big)(data).unwrap();
+    assert_eq!(file_info.format_version, 7);
+    assert_eq!(file_info.total_size as usize, written.len());
+    assert_eq!(file_info.sector_count, 8);
+    assert_eq!(file_info.tag, granny_file.tag);
+}
+
+#[test]
+fn test_write_elements() {
+    let granny_file = GrannyFile {
+        root_elements: vec![
+            element("Name", ElementType::String("test".into())),
+            element("Empty", ElementType::Reference(vec![])),
+            element("Half", ElementType::F16(0.5)),
+            element("Values", ElementType::Array(vec![ElementType::U8(1), ElementType::U8(2)])),
+            element("Weights", ElementType::Array(vec![ElementType::NormalU8(255), ElementType::NormalU8(0)])),
+            element("Normal", ElementType::BinormalI16(-32767)),
+            element("Items", ElementType::ReferenceToArray(vec![
+                vec![element("Child", ElementType::Reference(vec![]))],
+                vec![element("Child", ElementType::Reference(vec![
+                    element("Value", ElementType::I16(-3))
+                ]))]
+            ])),
+            element("Extended", ElementType::Variant {
+                type_name: Some("Value".into()),
+                elements: vec![element("Value", ElementType::I32(7))]
+            })
+        ],
+        format: FileFormat::LITTLE_ENDIAN_32,
+        tag: 0x80000039,
+        warnings: Vec::new(),
+        types: None
+    };
+
+    for format in [FileFormat::LITTLE_ENDIAN_32, FileFormat::LITTLE_ENDIAN_64, FileFormat::BIG_ENDIAN_32, FileFormat::BIG_ENDIAN_64] {
+        let written = granny_file.save_to_bytes(format).unwrap();
+        assert_eq!(GrannyFile::load_from_bytes_with(&written, Verification::Strict).unwrap().root_elements, granny_file.root_elements);
+    }
+}
+
+#[test]
+fn test_mismatched_array_items() {
+    let granny_file = GrannyFile {
+        root_elements: vec![
+            element("Items", ElementType::ReferenceToArray(vec![
+                vec![element("Value", ElementType::I32(1))],
+                vec![element("Value", ElementType::F32(1.0))]
+            ]))
+        ],
+        format: FileFormat::LITTLE_ENDIAN_32,
+        tag: 0x80000039,
+        warnings: Vec::new(),
+        types: None
+    };
+
+    assert!(granny_file.save_to_bytes(FileFormat::LITTLE_ENDIAN_32).is_err());
+}
+
+/// Returns the member names of the children of the member at `path`
+fn children_names(tree: &TypeTree, path: &str) -> Vec<String> {
+    let children = tree.resolve(path).unwrap().children.unwrap();
+    tree.get(&children).unwrap().members.iter().map(|member| member.name.to_string()).collect()
+}
+
+#[test]
+fn test_source_types() {
+    let mut granny_file = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_le.gr2")).unwrap();
+    let types = granny_file.types.as_mut().unwrap();
+    let root = types.root;
+    types.types.get_mut(&root).unwrap().members[0].extra = [1, 2, 3];
+
+    let written = granny_file.save_to_bytes(FileFormat::LITTLE_ENDIAN_32).unwrap();
+    let loaded = GrannyFile::load_from_bytes(&written).unwrap();
+    let (source, tree) = (granny_file.type_tree().unwrap(), loaded.type_tree().unwrap());
+
+    assert_eq!(tree.root_type().members[0].extra, [1, 2, 3]);
+
+    // Null references and empty arrays keep the types they were loaded with
+    for path in ["Textures", "Materials[0].Texture", "Meshes[0].PrimaryTopology.Indices16", "Meshes[0].MorphTargets"] {
+        assert!(!children_names(source, path).is_empty());
+        assert_eq!(children_names(tree, path), children_names(source, path), "{}", path);
+    }
+    assert_eq!(tree.resolve("Meshes[0].PrimaryTopology.Indices16").map(|member| member.member_type), Some(MemberType::ReferenceToArray));
+}
+
+#[test]
+fn test_changed_source_types() {
+    let mut granny_file = GrannyFile::load_from_bytes(include_bytes!("../assets/suzanne_le.gr2")).unwrap();
+    granny_file.root_elements.push(element("Added", ElementType::I32(1)));
+
+    // The root type is inferred, the members keep the types they were loaded with
+    let written = granny_file.save_to_bytes(FileFormat::LITTLE_ENDIAN_32).unwrap();
+    let loaded = GrannyFile::load_from_bytes(&written).unwrap();
+    assert_eq!(loaded.root_elements, granny_file.root_elements);
+
+    let (source, tree) = (granny_file.type_tree().unwrap(), loaded.type_tree().unwrap());
+    assert_eq!(tree.root_type().members.len(), source.root_type().members.len() + 1);
+    assert_eq!(children_names(tree, "Textures"), children_names(source, "Textures"));
+}
+
+#[test]
+fn test_untyped_empty_array() {
+    let granny_file = GrannyFile {
+        root_elements: vec![element("Values", ElementType::Array(vec![]))],
+        format: FileFormat::LITTLE_ENDIAN_32,
+        tag: 0x80000039,
+        warnings: Vec::new(),
+        types: None
+    };
+
+    assert!(matches!(granny_file.save_to_bytes(FileFormat::LITTLE_ENDIAN_32), Err(GrannyError::WrongFieldType(name)) if name == "Values"));
+}
\ No newline at end of file